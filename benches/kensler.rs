@@ -0,0 +1,125 @@
+//! Benchmarks for shuffling with a hashed permutation.
+
+use divan::counter::ItemsCount;
+use divan::{black_box, Bencher};
+use hashed_permutation::HashedPermutation;
+use std::num::NonZeroU32;
+
+fn main() {
+    divan::main();
+}
+
+/// The seed used by the deterministic benchmarks, so the same work is measured on every run
+const FIXED_SEED: u32 = 0x5eed;
+
+/// The lengths of the permutations to benchmark. This mixes powers of two with lengths that
+/// aren't, since the latter have to discard more hashes.
+fn lens() -> Vec<u32> {
+    vec![16, 100, 1 << 10, 10_000, 1 << 20, 1_000_003]
+}
+
+/// Shuffle every index of a permutation
+fn bench_shuffle(bencher: Bencher, perm: HashedPermutation) {
+    let n = perm.length.get();
+
+    bencher
+        .counter(ItemsCount::new(n as usize))
+        .bench_local(|| {
+            for i in 0..n {
+                black_box(perm.shuffle(black_box(i)).unwrap());
+            }
+        });
+}
+
+/// Shuffle with a random seed, which is closer to how the permutation is used in practice
+#[divan::bench(args = lens())]
+fn shuffle_random_seed(bencher: Bencher, len: u32) {
+    let perm = HashedPermutation::new_with_seed(NonZeroU32::new(len).unwrap(), rand::random());
+    bench_shuffle(bencher, perm);
+}
+
+/// Shuffle with a fixed seed, which makes runs easier to compare against each other
+#[divan::bench(args = lens())]
+fn shuffle_fixed_seed(bencher: Bencher, len: u32) {
+    let perm = HashedPermutation::new_with_seed(NonZeroU32::new(len).unwrap(), FIXED_SEED);
+    bench_shuffle(bencher, perm);
+}
+
+/// Shuffle with a fixed seed using a constant number of hash rounds, which trades extra hashing
+/// for avoiding the data-dependent loop
+#[divan::bench(args = lens())]
+fn shuffle_constant_rounds(bencher: Bencher, len: u32) {
+    let perm = HashedPermutation::new_with_seed(NonZeroU32::new(len).unwrap(), FIXED_SEED);
+
+    bencher
+        .counter(ItemsCount::new(len as usize))
+        .bench_local(|| {
+            for i in 0..len {
+                black_box(perm.shuffle_constant_rounds(black_box(i), 4).unwrap());
+            }
+        });
+}
+
+/// Iterate over the whole permutation with `HashedIter`
+#[divan::bench(args = lens())]
+fn iter(bencher: Bencher, len: u32) {
+    let perm = HashedPermutation::new_with_seed(NonZeroU32::new(len).unwrap(), FIXED_SEED);
+
+    bencher
+        .counter(ItemsCount::new(len as usize))
+        .bench_local(|| {
+            for x in perm.iter() {
+                black_box(x);
+            }
+        });
+}
+
+/// Iterate over the whole permutation with `unchecked_iter`, which skips the per-element checks
+#[divan::bench(args = lens())]
+fn unchecked_iter(bencher: Bencher, len: u32) {
+    let perm = HashedPermutation::new_with_seed(NonZeroU32::new(len).unwrap(), FIXED_SEED);
+
+    bencher
+        .counter(ItemsCount::new(len as usize))
+        .bench_local(|| {
+            for x in perm.unchecked_iter() {
+                black_box(x);
+            }
+        });
+}
+
+/// Fill a slice with the shuffle of every index on a single thread, which is the baseline for
+/// `fill_parallel`
+#[cfg(feature = "rayon")]
+#[divan::bench(args = lens())]
+fn fill_sequential(bencher: Bencher, len: u32) {
+    let perm = HashedPermutation::new_with_seed(NonZeroU32::new(len).unwrap(), FIXED_SEED);
+    let inputs: Vec<u32> = (0..len).collect();
+    let mut out = vec![0; len as usize];
+
+    bencher
+        .counter(ItemsCount::new(len as usize))
+        .bench_local(|| {
+            for (&input, output) in inputs.iter().zip(out.iter_mut()) {
+                *output = perm.shuffle(input).unwrap();
+            }
+            black_box(&mut out);
+        });
+}
+
+/// Fill the same slice as `fill_sequential` with `shuffle_slice_par`, which shows how the
+/// parallel fill scales with the length
+#[cfg(feature = "rayon")]
+#[divan::bench(args = lens())]
+fn fill_parallel(bencher: Bencher, len: u32) {
+    let perm = HashedPermutation::new_with_seed(NonZeroU32::new(len).unwrap(), FIXED_SEED);
+    let inputs: Vec<u32> = (0..len).collect();
+    let mut out = vec![0; len as usize];
+
+    bencher
+        .counter(ItemsCount::new(len as usize))
+        .bench_local(|| {
+            perm.shuffle_slice_par(&inputs, &mut out).unwrap();
+            black_box(&mut out);
+        });
+}
@@ -0,0 +1,87 @@
+//! Benchmarks for shuffling with a hashed permutation, using criterion.
+//!
+//! These measure the same workloads as the divan benchmarks in `kensler.rs`, so the numbers from
+//! either harness can be compared. They only build with the `criterion-bench` feature:
+//!
+//! ```sh
+//! cargo bench --features criterion-bench --bench kensler_criterion
+//! ```
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use hashed_permutation::HashedPermutation;
+use std::num::NonZeroU32;
+
+/// The seed used by the deterministic benchmarks, so the same work is measured on every run
+const FIXED_SEED: u32 = 0x5eed;
+
+/// The lengths of the permutations to benchmark. This mixes powers of two with lengths that
+/// aren't, since the latter have to discard more hashes.
+fn lens() -> Vec<u32> {
+    vec![16, 100, 1 << 10, 10_000, 1 << 20, 1_000_003]
+}
+
+/// Shuffle every index of a permutation with a fixed seed
+fn shuffle_fixed_seed(c: &mut Criterion) {
+    let mut group = c.benchmark_group("shuffle_fixed_seed");
+
+    for len in lens() {
+        let perm = HashedPermutation::new_with_seed(NonZeroU32::new(len).unwrap(), FIXED_SEED);
+        group.throughput(Throughput::Elements(u64::from(len)));
+        group.bench_with_input(BenchmarkId::from_parameter(len), &perm, |b, perm| {
+            b.iter(|| {
+                for i in 0..len {
+                    black_box(perm.shuffle(black_box(i)).unwrap());
+                }
+            })
+        });
+    }
+    group.finish();
+}
+
+/// Iterate over the whole permutation with `HashedIter`
+fn iter(c: &mut Criterion) {
+    let mut group = c.benchmark_group("iter");
+
+    for len in lens() {
+        let perm = HashedPermutation::new_with_seed(NonZeroU32::new(len).unwrap(), FIXED_SEED);
+        group.throughput(Throughput::Elements(u64::from(len)));
+        group.bench_with_input(BenchmarkId::from_parameter(len), &perm, |b, perm| {
+            b.iter(|| {
+                for x in perm.iter() {
+                    black_box(x);
+                }
+            })
+        });
+    }
+    group.finish();
+}
+
+/// Materialize the inverse of large permutations, where scattering into the table is dominated by
+/// cache misses. `naive` writes each entry as soon as it's shuffled, which is the baseline for the
+/// prefetching that `inverse` does.
+fn inverse(c: &mut Criterion) {
+    let mut group = c.benchmark_group("inverse");
+    group.sample_size(10);
+
+    for len in [1 << 20, 1 << 24] {
+        let perm = HashedPermutation::new_with_seed(NonZeroU32::new(len).unwrap(), FIXED_SEED);
+        group.throughput(Throughput::Elements(u64::from(len)));
+        group.bench_with_input(BenchmarkId::new("naive", len), &perm, |b, perm| {
+            b.iter(|| {
+                let mut table = vec![0; len as usize];
+
+                for i in 0..len {
+                    table[perm.shuffle(i).unwrap() as usize] = i;
+                }
+                black_box(table)
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("prefetch", len), &perm, |b, perm| {
+            b.iter(|| black_box(perm.inverse()))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, shuffle_fixed_seed, iter, inverse);
+criterion_main!(benches);
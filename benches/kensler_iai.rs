@@ -0,0 +1,45 @@
+//! Instruction-count benchmarks for shuffling with a hashed permutation, using iai.
+//!
+//! The divan benchmarks measure wall-clock time, which is noisy on CI. These run a single
+//! `shuffle` call under Cachegrind and report how many instructions it took, which is stable
+//! enough to catch regressions like an extra branch in the hot loop. They need Valgrind, and only
+//! build with the `iai-bench` feature:
+//!
+//! ```sh
+//! cargo bench --features iai-bench --bench kensler_iai
+//! ```
+
+use hashed_permutation::HashedPermutation;
+use iai::black_box;
+use std::num::NonZeroU32;
+
+/// The seed used by every benchmark, so the same work is measured on every run
+const FIXED_SEED: u32 = 0x5eed;
+
+/// Shuffle a single index in the middle of a permutation of length `len`
+fn shuffle(len: u32) -> u32 {
+    let length = NonZeroU32::new(black_box(len)).unwrap();
+    let perm = HashedPermutation::new_with_seed(length, black_box(FIXED_SEED));
+    perm.shuffle(black_box(len / 2)).unwrap()
+}
+
+/// A power of two, where every hash lands in range
+fn shuffle_power_of_two() -> u32 {
+    shuffle(1 << 10)
+}
+
+/// Just past a power of two, where about half of the hashes have to be discarded
+fn shuffle_past_power_of_two() -> u32 {
+    shuffle((1 << 10) + 1)
+}
+
+/// A large length that isn't a power of two
+fn shuffle_large() -> u32 {
+    shuffle(1_000_003)
+}
+
+iai::main!(
+    shuffle_power_of_two,
+    shuffle_past_power_of_two,
+    shuffle_large
+);
@@ -0,0 +1,117 @@
+//! A permutation over `u64` indices that shuffles within fixed-size blocks.
+
+use crate::domain::mix32;
+use crate::kensler::kensler_permute;
+use std::num::NonZeroU32;
+
+/// Permutes the whole `u64` range by splitting it into blocks and shuffling within each block.
+///
+/// The block containing `global_index` is `global_index / block_size`, and the permutation never
+/// moves an index out of its block, so the blocks themselves stay in order. Each block is shuffled
+/// with its own seed, derived from the seed of the permutation and the index of the block, so the
+/// blocks don't all follow the same pattern. The range of `u64` generally isn't a multiple of the
+/// block size, so the last block is shorter than the others.
+///
+/// ```
+/// # use hashed_permutation::BlockPermutation;
+/// use std::num::NonZeroU32;
+///
+/// let perm = BlockPermutation::new(NonZeroU32::new(1000).unwrap(), 1234);
+/// let shuffled = perm.shuffle(5_000_000_123);
+/// assert_eq!(shuffled / 1000, 5_000_000);
+/// ```
+#[derive(Clone, Debug)]
+pub struct BlockPermutation {
+    /// The number of indices in each block
+    block_size: NonZeroU32,
+
+    /// The seed that the seed of each block is derived from
+    seed: u32,
+}
+
+impl BlockPermutation {
+    /// Create a new block permutation with the given block size and seed
+    pub fn new(block_size: NonZeroU32, seed: u32) -> Self {
+        Self { block_size, seed }
+    }
+
+    /// The number of indices in each block
+    pub fn block_size(&self) -> NonZeroU32 {
+        self.block_size
+    }
+
+    /// The seed that the seed of each block is derived from
+    pub fn seed(&self) -> u32 {
+        self.seed
+    }
+
+    /// Shuffle an index within its block.
+    ///
+    /// The result is always in the same block as `global_index`, and for each block this is a
+    /// bijection over the indices in the block.
+    pub fn shuffle(&self, global_index: u64) -> u64 {
+        let block_size = u64::from(self.block_size.get());
+        let block = global_index / block_size;
+        let start = block * block_size;
+        // Every block is full except for the one at the very end of the `u64` range
+        let len = ((u64::MAX - start).min(block_size - 1) + 1) as u32;
+        let offset = (global_index - start) as u32;
+        start + u64::from(kensler_permute(offset, len, self.block_seed(block)))
+    }
+
+    /// The seed used to shuffle the indices of a particular block
+    fn block_seed(&self, block: u64) -> u32 {
+        let block_hash = mix32(block as u32 ^ mix32((block >> 32) as u32));
+        mix32(self.seed ^ block_hash)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    // Each block should be shuffled onto itself, and nothing should leave its block.
+    fn test_within_block_bijection() {
+        let block_sizes = [100, 5, 13, 128, 249, 1];
+        let seeds = [100, 5, 13, 128, 249, 0];
+
+        for (&block_size, &seed) in block_sizes.iter().zip(seeds.iter()) {
+            let perm = BlockPermutation::new(NonZeroU32::new(block_size).unwrap(), seed);
+            let block_size = u64::from(block_size);
+
+            for &block in &[0, 1, 7, 1 << 40] {
+                let start = block * block_size;
+                let outputs: HashSet<u64> = (start..start + block_size)
+                    .map(|i| perm.shuffle(i))
+                    .collect();
+                assert_eq!(outputs.len() as u64, block_size);
+                assert!(outputs.iter().all(|&x| x / block_size == block));
+            }
+        }
+    }
+
+    #[test]
+    // The last block is cut short by the end of the `u64` range, and should still be a bijection.
+    fn test_last_block() {
+        let block_size = 1000;
+        let perm = BlockPermutation::new(NonZeroU32::new(block_size).unwrap(), 1234);
+        let start = u64::MAX / u64::from(block_size) * u64::from(block_size);
+        let outputs: HashSet<u64> = (start..=u64::MAX).map(|i| perm.shuffle(i)).collect();
+        assert_eq!(outputs.len() as u64, u64::MAX - start + 1);
+        assert!(outputs.iter().all(|&x| x >= start));
+    }
+
+    #[test]
+    // Different blocks should use different seeds, so they shouldn't all be shuffled the same way.
+    fn test_blocks_differ() {
+        let perm = BlockPermutation::new(NonZeroU32::new(100).unwrap(), 1234);
+        let pattern = |block: u64| -> Vec<u64> {
+            (block * 100..(block + 1) * 100)
+                .map(|i| perm.shuffle(i) - block * 100)
+                .collect()
+        };
+        assert_ne!(pattern(0), pattern(1));
+    }
+}
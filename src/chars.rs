@@ -0,0 +1,97 @@
+//! Permutations over ranges of Unicode scalar values.
+
+use crate::error::{PermutationError, PermutationResult};
+use crate::HashedPermutation;
+use std::convert::TryFrom;
+use std::num::NonZeroU32;
+
+/// The first code point of the surrogate range, which contains no Unicode scalar values
+const SURROGATE_START: u32 = 0xd800;
+
+/// One past the last code point of the surrogate range
+const SURROGATE_END: u32 = 0xe000;
+
+/// Create an iterator over the `char`s in `start..end`, in a shuffled order.
+///
+/// The code points in `start..end` that fall in the surrogate range `U+D800..U+E000` aren't valid
+/// `char`s, so they are skipped, and every other `char` in the range is yielded exactly once. The
+/// order is determined by a [`HashedPermutation`] with the given `seed`. An empty range yields
+/// nothing, and this returns an error if `start > end`.
+///
+/// ```
+/// # use hashed_permutation::permute_chars;
+/// let mut letters: Vec<char> = permute_chars('a', 'f', 1234).unwrap().collect();
+/// letters.sort_unstable();
+/// assert_eq!(letters, ['a', 'b', 'c', 'd', 'e']);
+/// ```
+pub fn permute_chars(
+    start: char,
+    end: char,
+    seed: u32,
+) -> PermutationResult<impl Iterator<Item = char>> {
+    let (start, end) = (u32::from(start), u32::from(end));
+
+    if start > end {
+        return Err(PermutationError::RangeOutOfBounds {
+            start,
+            end,
+            length: u32::from(char::MAX) + 1,
+        });
+    }
+    // The number of surrogates in the range, which are skipped when mapping indices back to
+    // code points. `char` can't be a surrogate, so the range either covers the whole surrogate
+    // range or none of it.
+    let gap = if start < SURROGATE_START && end >= SURROGATE_END {
+        SURROGATE_END - SURROGATE_START
+    } else {
+        0
+    };
+    let length = end - start - gap;
+    let perm = NonZeroU32::new(length).map(|length| HashedPermutation::new_with_seed(length, seed));
+
+    Ok(perm.into_iter().flat_map(move |perm| {
+        (0..length).map(move |i| {
+            let mut code_point = start + perm.permute(i);
+
+            if code_point >= SURROGATE_START && gap > 0 {
+                code_point += gap;
+            }
+            // Every code point outside of the surrogate range up to `char::MAX` is valid
+            char::try_from(code_point).expect("skipped the surrogate range")
+        })
+    }))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    // Every letter should show up exactly once. The range is half-open, so it ends at '{', which
+    // comes right after 'z'.
+    fn test_alphabet() {
+        for seed in 0..16 {
+            let letters: Vec<char> = permute_chars('a', '{', seed).unwrap().collect();
+            let set: HashSet<char> = letters.iter().copied().collect();
+            assert_eq!(letters.len(), 26);
+            assert_eq!(set, ('a'..='z').collect());
+        }
+    }
+
+    #[test]
+    // A range spanning the surrogates should skip them and still yield every other `char`.
+    fn test_surrogate_gap() {
+        let (start, end) = ('\u{d7f0}', '\u{e010}');
+        let chars: Vec<char> = permute_chars(start, end, 7).unwrap().collect();
+        let set: HashSet<char> = chars.iter().copied().collect();
+        assert_eq!(chars.len(), 0x20);
+        assert_eq!(set, (start..end).collect());
+    }
+
+    #[test]
+    fn test_empty_and_invalid() {
+        assert_eq!(permute_chars('a', 'a', 0).unwrap().count(), 0);
+        assert!(permute_chars('z', 'a', 0).is_err());
+    }
+}
@@ -0,0 +1,169 @@
+//! Comparisons between two permutations of the same length.
+
+use crate::error::{PermutationError, PermutationResult};
+use crate::stats::count_inversions;
+use crate::{HashedPermutation, Permute};
+
+impl HashedPermutation {
+    /// Count the number of leading indices where this permutation agrees with `other`.
+    ///
+    /// This is the largest `k` such that `self.shuffle(i) == other.shuffle(i)` for every `i` in
+    /// `0..k`. Two permutations with different lengths can't be compared, so this returns an
+    /// error if the lengths differ.
+    pub fn common_prefix_len(&self, other: &HashedPermutation) -> PermutationResult<u32> {
+        self.check_same_length(other)?;
+        let n = self.length.get();
+        Ok((0..n)
+            .position(|i| self.permute(i) != other.permute(i))
+            .map_or(n, |i| i as u32))
+    }
+
+    /// Check whether this permutation orders every index the same way as `other`.
+    ///
+    /// Unlike comparing the seeds, this compares the actual orderings, so it catches two seeds
+    /// that happen to produce the same permutation. This returns an error if the lengths differ.
+    pub fn same_ordering(&self, other: &HashedPermutation) -> PermutationResult<bool> {
+        Ok(self.common_prefix_len(other)? == self.length.get())
+    }
+
+    /// Compute the Kendall tau distance between this permutation and `other`.
+    ///
+    /// This is the number of pairs of indices `i < j` that the two permutations order differently,
+    /// i.e. where `shuffle(i) < shuffle(j)` for one permutation but not the other. It's zero for two
+    /// identical orderings, and `length * (length - 1) / 2` when one is the reflection of the other.
+    /// `other` can be any [`Permute`] backend, and this returns an error if the lengths differ.
+    ///
+    /// This relabels the outputs of `other` by where this permutation sends each index, and counts
+    /// the inversions of the result, so it takes O(n log n) time and allocates a table of `length`
+    /// entries.
+    pub fn kendall_tau<P: Permute + ?Sized>(&self, other: &P) -> PermutationResult<u64> {
+        let n = self.length.get();
+
+        if other.len() != n {
+            return Err(PermutationError::LengthMismatch {
+                expected: n as usize,
+                found: other.len() as usize,
+            });
+        }
+        // `relabeled[self.shuffle(i)]` is `other.shuffle(i)`, so a pair of indices is ordered
+        // differently by the two permutations exactly when it's an inversion of this table
+        let mut relabeled = vec![0; n as usize];
+
+        for i in 0..n {
+            relabeled[self.permute(i) as usize] = other.shuffle(i)?;
+        }
+        Ok(count_inversions(relabeled.into_iter(), n))
+    }
+
+    /// Return an error if `other` doesn't have the same length as this permutation
+    fn check_same_length(&self, other: &HashedPermutation) -> PermutationResult<()> {
+        if self.length != other.length {
+            return Err(PermutationError::LengthMismatch {
+                expected: self.length.get() as usize,
+                found: other.length.get() as usize,
+            });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::{edge_lengths_and_seeds, lengths_and_seeds};
+    use std::num::NonZeroU32;
+
+    #[test]
+    fn test_common_prefix_len() {
+        let (lengths, seeds) = lengths_and_seeds();
+
+        for (&length, seed) in lengths.iter().zip(seeds) {
+            let perm = HashedPermutation::new_with_seed(length, seed);
+            let length = length.get();
+            assert_eq!(perm.common_prefix_len(&perm).unwrap(), length);
+
+            let other = HashedPermutation {
+                seed: seed + 1,
+                ..perm.clone()
+            };
+            let prefix = perm.common_prefix_len(&other).unwrap();
+            assert!(prefix < length);
+            assert!((0..prefix).all(|i| perm.shuffle(i).unwrap() == other.shuffle(i).unwrap()));
+            assert_ne!(
+                perm.shuffle(prefix).unwrap(),
+                other.shuffle(prefix).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_kendall_tau() {
+        let (lengths, seeds) = edge_lengths_and_seeds();
+
+        for (&length, seed) in lengths.iter().zip(seeds) {
+            let perm = HashedPermutation::new_with_seed(length, seed);
+            let length = length.get();
+            let max = u64::from(length) * u64::from(length - 1) / 2;
+            assert_eq!(perm.kendall_tau(&perm).unwrap(), 0);
+            assert_eq!(perm.kendall_tau(&perm.reflect()).unwrap(), max);
+
+            // Compare against counting the disagreeing pairs directly
+            let other = HashedPermutation {
+                seed: seed.wrapping_add(1),
+                ..perm.clone()
+            };
+            let a: Vec<u32> = perm.iter().collect();
+            let b: Vec<u32> = other.iter().collect();
+            let mut expected = 0;
+
+            for i in 0..a.len() {
+                for j in i + 1..a.len() {
+                    if (a[i] < a[j]) != (b[i] < b[j]) {
+                        expected += 1;
+                    }
+                }
+            }
+            assert_eq!(perm.kendall_tau(&other).unwrap(), expected);
+            assert_eq!(other.kendall_tau(&perm).unwrap(), expected);
+        }
+        let perm = HashedPermutation::new_with_seed(NonZeroU32::new(10).unwrap(), 0);
+        let other = HashedPermutation::new_with_seed(NonZeroU32::new(11).unwrap(), 0);
+        assert!(perm.kendall_tau(&other).is_err());
+    }
+
+    #[test]
+    fn test_same_ordering() {
+        let (lengths, seeds) = lengths_and_seeds();
+        let mut different = 0;
+
+        for (&length, seed) in lengths.iter().zip(seeds) {
+            let perm = HashedPermutation::new_with_seed(length, seed);
+            let length = length.get();
+            assert!(perm.same_ordering(&perm).unwrap());
+
+            let other = HashedPermutation {
+                seed: seed + 1,
+                ..perm.clone()
+            };
+            if !perm.same_ordering(&other).unwrap() {
+                different += 1;
+            }
+            let longer = perm.with_length(NonZeroU32::new(length + 1).unwrap());
+            assert!(perm.same_ordering(&longer).is_err());
+        }
+        assert!(different >= lengths.len() - 1);
+    }
+
+    #[test]
+    fn test_common_prefix_len_mismatch() {
+        let perm = HashedPermutation::new_with_seed(NonZeroU32::new(10).unwrap(), 0);
+        let other = perm.with_length(NonZeroU32::new(11).unwrap());
+        assert!(matches!(
+            perm.common_prefix_len(&other),
+            Err(PermutationError::LengthMismatch {
+                expected: 10,
+                found: 11
+            })
+        ));
+    }
+}
@@ -0,0 +1,287 @@
+//! Queries about the cycle structure of a permutation.
+
+use crate::error::{PermutationError, PermutationResult};
+use crate::HashedPermutation;
+
+/// The parity, or sign, of a permutation.
+///
+/// A permutation is even if it can be written as an even number of transpositions, and odd
+/// otherwise.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Parity {
+    /// The permutation has a sign of `+1`
+    Even,
+
+    /// The permutation has a sign of `-1`
+    Odd,
+}
+
+impl HashedPermutation {
+    /// Get the cycle of the permutation that contains `start`.
+    ///
+    /// This follows `start, shuffle(start), shuffle(shuffle(start)), ...` until it returns to
+    /// `start`, without decomposing the rest of the permutation. The cycle is returned in that
+    /// order, beginning with `start`. A fixed point is a cycle with a single element.
+    pub fn cycle_containing(&self, start: u32) -> PermutationResult<Vec<u32>> {
+        if start >= self.length.get() {
+            return Err(PermutationError::ShuffleOutOfRange {
+                shuffle: start,
+                max_shuffle: self.length.get(),
+            });
+        }
+        let mut cycle = vec![start];
+        let mut current = self.permute(start);
+
+        while current != start {
+            cycle.push(current);
+            current = self.permute(current);
+        }
+        Ok(cycle)
+    }
+
+    /// Count the fixed points of the permutation, i.e. the inputs that map to themselves.
+    ///
+    /// These are the cycles with a single element. This takes O(n) time.
+    pub fn fixed_point_count(&self) -> u32 {
+        (0..self.length.get())
+            .filter(|&i| self.permute(i) == i)
+            .count() as u32
+    }
+
+    /// Iterate over the permuted indices, skipping any index that maps to itself.
+    ///
+    /// This yields `shuffle(i)` for every `i` in `0..length` where `shuffle(i) != i`, in the same
+    /// order as [`HashedPermutation::iter`], so it yields `length - fixed_point_count()` values.
+    pub fn iter_no_fixed(&self) -> impl Iterator<Item = u32> + '_ {
+        (0..self.length.get())
+            .map(move |i| (i, self.permute(i)))
+            .filter(|&(i, output)| output != i)
+            .map(|(_, output)| output)
+    }
+
+    /// Check whether applying the permutation twice gives back the identity.
+    ///
+    /// This is true exactly when every cycle has a length of one or two, which is checked by
+    /// verifying `shuffle(shuffle(x)) == x` for every `x` in `0..length`. It stops at the first
+    /// counterexample, so it's usually fast to return `false`.
+    ///
+    /// Every permutation of one or two elements is an involution, but longer hashed permutations
+    /// almost never are: a random permutation of `n` elements is an involution with a probability
+    /// that vanishes rapidly as `n` grows.
+    pub fn is_involution(&self) -> bool {
+        (0..self.length.get()).all(|x| self.permute(self.permute(x)) == x)
+    }
+
+    /// Compute the parity of the permutation.
+    ///
+    /// This decomposes the whole permutation into its cycles. A cycle of length `l` is a product
+    /// of `l - 1` transpositions, so the permutation is even exactly when `length - cycles` is
+    /// even. This takes O(n) time and allocates one bit per element to track which elements have
+    /// already been visited.
+    pub fn parity(&self) -> Parity {
+        let mut cycles = 0u32;
+        self.for_each_cycle(|_, _| cycles += 1);
+
+        if (self.length.get() - cycles) & 1 == 0 {
+            Parity::Even
+        } else {
+            Parity::Odd
+        }
+    }
+
+    /// Get the length of every cycle of the permutation.
+    ///
+    /// The lengths are listed in the order of each cycle's smallest element, and they sum to
+    /// `length`. Only the lengths are stored, not the cycles themselves: besides the result, this
+    /// allocates one bit per element to track which elements have already been visited.
+    pub fn cycle_lengths(&self) -> Vec<u32> {
+        let mut lengths = Vec::new();
+        self.for_each_cycle(|_, len| lengths.push(len));
+        lengths
+    }
+
+    /// Format the permutation in cycle notation, such as `(0 3 1)(2 4)`.
+    ///
+    /// Each cycle starts from its smallest element and lists the elements in the order the
+    /// permutation visits them, and the cycles are ordered by their smallest elements. Fixed points
+    /// are left out by convention, so the identity permutation is written as `()`. This is meant
+    /// for logging and debugging small permutations, since the string holds every element that
+    /// isn't a fixed point.
+    pub fn cycle_notation(&self) -> String {
+        let mut notation = String::new();
+
+        self.for_each_cycle(|start, len| {
+            if len == 1 {
+                return;
+            }
+            let mut current = start;
+            notation.push('(');
+
+            for k in 0..len {
+                if k > 0 {
+                    notation.push(' ');
+                }
+                notation.push_str(&current.to_string());
+                current = self.permute(current);
+            }
+            notation.push(')');
+        });
+        if notation.is_empty() {
+            notation.push_str("()");
+        }
+        notation
+    }
+
+    /// Walk every cycle of the permutation, calling `f` with the smallest element and the length
+    /// of each one, in the order of their smallest elements.
+    fn for_each_cycle(&self, mut f: impl FnMut(u32, u32)) {
+        let n = self.length.get();
+        let mut visited = vec![0_u64; (n as usize).div_ceil(64)];
+        let is_visited = |visited: &[u64], i: u32| visited[i as usize / 64] & (1 << (i % 64)) != 0;
+
+        for start in 0..n {
+            if is_visited(&visited, start) {
+                continue;
+            }
+            let mut len = 0;
+            let mut current = start;
+
+            while !is_visited(&visited, current) {
+                visited[current as usize / 64] |= 1 << (current % 64);
+                len += 1;
+                current = self.permute(current);
+            }
+            f(start, len);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::edge_lengths_and_seeds;
+    use std::collections::HashSet;
+    use std::num::NonZeroU32;
+
+    #[test]
+    // Every element of the cycle should map to the next one, the last one should map back to the
+    // start, and no element should repeat.
+    fn test_cycle_containing() {
+        let (lengths, seeds) = edge_lengths_and_seeds();
+
+        for (&length, seed) in lengths.iter().zip(seeds) {
+            let perm = HashedPermutation::new_with_seed(length, seed);
+            let length = length.get();
+
+            for start in 0..length {
+                let cycle = perm.cycle_containing(start).unwrap();
+                assert_eq!(cycle[0], start);
+
+                for (k, &elem) in cycle.iter().enumerate() {
+                    let next = cycle[(k + 1) % cycle.len()];
+                    assert_eq!(perm.shuffle(elem).unwrap(), next);
+                }
+                let set: HashSet<u32> = cycle.iter().copied().collect();
+                assert_eq!(set.len(), cycle.len());
+            }
+            assert!(perm.cycle_containing(length).is_err());
+        }
+    }
+
+    #[test]
+    // The lengths should match the cycles found by walking from each cycle's smallest element.
+    fn test_cycle_lengths() {
+        let (lengths, seeds) = edge_lengths_and_seeds();
+
+        for (&length, seed) in lengths.iter().zip(seeds) {
+            let perm = HashedPermutation::new_with_seed(length, seed);
+            let length = length.get();
+            let cycle_lengths = perm.cycle_lengths();
+            assert_eq!(cycle_lengths.iter().sum::<u32>(), length);
+
+            let mut visited = HashSet::new();
+            let mut expected = Vec::new();
+
+            for start in 0..length {
+                if visited.contains(&start) {
+                    continue;
+                }
+                let cycle = perm.cycle_containing(start).unwrap();
+                expected.push(cycle.len() as u32);
+                visited.extend(cycle);
+            }
+            assert_eq!(cycle_lengths, expected);
+        }
+    }
+
+    #[test]
+    fn test_cycle_notation() {
+        // This permutation maps 0..6 to [5, 1, 0, 4, 3, 2]: 0 -> 5 -> 2 -> 0, 1 is fixed, and
+        // 3 and 4 swap.
+        let perm = HashedPermutation::new_with_seed(NonZeroU32::new(6).unwrap(), 1);
+        assert_eq!(perm.iter().collect::<Vec<u32>>(), vec![5, 1, 0, 4, 3, 2]);
+        assert_eq!(perm.cycle_notation(), "(0 5 2)(3 4)");
+
+        let identity = HashedPermutation::new_with_seed(NonZeroU32::new(1).unwrap(), 0);
+        assert_eq!(identity.cycle_notation(), "()");
+    }
+
+    #[test]
+    fn test_iter_no_fixed() {
+        let (lengths, seeds) = edge_lengths_and_seeds();
+
+        for (&length, seed) in lengths.iter().zip(seeds) {
+            let perm = HashedPermutation::new_with_seed(length, seed);
+            let length = length.get();
+            let fixed_points = perm.fixed_point_count();
+            assert_eq!(fixed_points, perm.verify().fixed_points);
+            assert_eq!(perm.iter_no_fixed().count() as u32, length - fixed_points);
+
+            let expected: Vec<u32> = perm
+                .iter()
+                .enumerate()
+                .filter(|&(i, output)| output != i as u32)
+                .map(|(_, output)| output)
+                .collect();
+            assert!(perm.iter_no_fixed().eq(expected));
+        }
+    }
+
+    #[test]
+    fn test_is_involution() {
+        for seed in 0..8 {
+            for &length in &[1, 2] {
+                let perm = HashedPermutation::new_with_seed(NonZeroU32::new(length).unwrap(), seed);
+                assert!(perm.is_involution());
+            }
+        }
+        let perm = HashedPermutation::new_with_seed(NonZeroU32::new(100).unwrap(), 1234);
+        assert!(!perm.is_involution());
+    }
+
+    #[test]
+    // The parity should match the parity of the number of inversions.
+    fn test_parity() {
+        for length in 1..=40 {
+            for seed in 0..8 {
+                let perm = HashedPermutation::new_with_seed(NonZeroU32::new(length).unwrap(), seed);
+                let values: Vec<u32> = perm.iter().collect();
+                let mut inversions = 0;
+
+                for i in 0..values.len() {
+                    for j in i + 1..values.len() {
+                        if values[i] > values[j] {
+                            inversions += 1;
+                        }
+                    }
+                }
+                let expected = if inversions & 1 == 0 {
+                    Parity::Even
+                } else {
+                    Parity::Odd
+                };
+                assert_eq!(perm.parity(), expected);
+            }
+        }
+    }
+}
@@ -0,0 +1,91 @@
+//! Type-level domain separation for permutation seeds.
+
+use crate::HashedPermutation;
+
+/// A marker type for a permutation domain, identified by a compile-time tag.
+///
+/// Subsystems that need independent families of permutations can each use their own domain
+/// rather than passing around seed offsets by hand. The tag is mixed into the seed, so the same
+/// base seed yields unrelated permutations in different domains.
+///
+/// ```
+/// # use hashed_permutation::{Domain, HashedPermutation};
+/// use std::num::NonZeroU32;
+///
+/// const AUDIO: u32 = 1;
+/// const VIDEO: u32 = 2;
+///
+/// let base = HashedPermutation::new_with_seed(NonZeroU32::new(10).unwrap(), 1234);
+/// let audio = base.in_domain::<AUDIO>();
+/// let video = base.in_domain::<VIDEO>();
+/// assert_eq!(audio.seed, Domain::<AUDIO>::mix_seed(1234));
+/// assert_ne!(audio.seed, video.seed);
+/// ```
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Domain<const TAG: u32>;
+
+impl<const TAG: u32> Domain<TAG> {
+    /// The tag that identifies this domain
+    pub const TAG: u32 = TAG;
+
+    /// Mix the domain's tag into a seed.
+    ///
+    /// The tag is hashed before being combined with the seed so that nearby tags don't produce
+    /// nearby seeds. For a fixed tag this is a bijection over `u32`, so distinct base seeds stay
+    /// distinct within a domain.
+    pub fn mix_seed(seed: u32) -> u32 {
+        seed ^ mix32(TAG.wrapping_add(0x9e37_79b9))
+    }
+}
+
+impl HashedPermutation {
+    /// Create a copy of this permutation whose seed is separated into the domain `TAG`.
+    ///
+    /// The length is unchanged and the seed is derived with [`Domain::mix_seed`].
+    pub fn in_domain<const TAG: u32>(&self) -> Self {
+        Self::new_with_seed(self.length, Domain::<TAG>::mix_seed(self.seed))
+    }
+}
+
+/// A fast, invertible integer hash over `u32`.
+///
+/// This is the "lowbias32" hash by Chris Wellons, which has very good avalanche properties for
+/// its cost.
+pub(crate) fn mix32(mut x: u32) -> u32 {
+    x ^= x >> 16;
+    x = x.wrapping_mul(0x7feb_352d);
+    x ^= x >> 15;
+    x = x.wrapping_mul(0x846c_a68b);
+    x ^= x >> 16;
+    x
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::num::NonZeroU32;
+
+    #[test]
+    // The same base seed and length should give different permutations in different domains.
+    fn test_distinct_domains() {
+        for &length in &[5, 13, 100, 128, 249] {
+            let base = HashedPermutation::new_with_seed(NonZeroU32::new(length).unwrap(), 100);
+            let first = base.in_domain::<1>();
+            let second = base.in_domain::<2>();
+            assert_eq!(first.length, base.length);
+            assert_eq!(second.length, base.length);
+            assert_ne!(first.seed, second.seed);
+
+            let first: Vec<u32> = first.take_iter(length).collect();
+            let second: Vec<u32> = second.take_iter(length).collect();
+            assert_ne!(first, second);
+        }
+    }
+
+    #[test]
+    fn test_same_domain_is_deterministic() {
+        let base = HashedPermutation::new_with_seed(NonZeroU32::new(100).unwrap(), 100);
+        assert_eq!(base.in_domain::<7>().seed, base.in_domain::<7>().seed);
+        assert_eq!(Domain::<7>::TAG, 7);
+    }
+}
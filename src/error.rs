@@ -1,3 +1,4 @@
+use std::io;
 use thiserror::Error;
 
 /// The different types of errors that can arise from this crate
@@ -14,8 +15,111 @@ pub enum PermutationError {
     /// of the permutation set (which is also the upper bound for the calling index).
     #[error("Attempted to shuffle index {shuffle}, but the length of the array is {max_shuffle}")]
     ShuffleOutOfRange { shuffle: u32, max_shuffle: u32 },
+
+    /// This error is invoked when one of a list of inputs is out of range, such as in
+    /// `shuffle_all_checked`.
+    ///
+    /// `index` is the position of the offending input in the list, `shuffle` is its value, and
+    /// `max_shuffle` is the size of the permutation set.
+    #[error("Input {index} is {shuffle}, but the length of the array is {max_shuffle}")]
+    ShuffleOutOfRangeAt {
+        index: usize,
+        shuffle: u32,
+        max_shuffle: u32,
+    },
+
+    /// This error is invoked when the caller passes a range of indices `start..end` that isn't
+    /// within `0..length`, or whose start is past its end.
+    ///
+    /// A valid range satisfies `start <= end <= length`.
+    #[error("The range {start}..{end} is not within 0..{length}")]
+    RangeOutOfBounds { start: u32, end: u32, length: u32 },
+
+    /// This error is invoked when the caller attempts to create a permutation over an inclusive
+    /// range whose length does not fit in a `u32`.
+    ///
+    /// An inclusive range `0..=end` has `end + 1` elements, so `end` cannot be `u32::MAX`.
+    #[error("The inclusive range 0..={end} has too many elements for a permutation")]
+    LengthOverflow { end: u32 },
+
+    /// This error is invoked when the caller attempts to create a permutation from a length of
+    /// zero, since a permutation needs at least one element.
+    #[error("The length of a permutation must be nonzero")]
+    ZeroLength,
+
+    /// This error is invoked when the caller attempts to create a permutation that is longer than
+    /// the maximum length they allow.
+    ///
+    /// `length` is the requested length of the permutation and `max` is the largest length that
+    /// was allowed.
+    #[error("The length of the permutation is {length}, but the maximum allowed length is {max}")]
+    LengthTooLarge { length: u32, max: u32 },
+
+    /// This error is invoked when an operation needs two things of the same length, such as two
+    /// permutations that are being compared, but their lengths differ.
+    ///
+    /// `expected` is the length of the permutation the operation was called on, and `found` is
+    /// the length of the other argument.
+    #[error("Expected a length of {expected}, but found a length of {found}")]
+    LengthMismatch { expected: usize, found: usize },
+
+    /// This error is invoked when an operation that splits the permutation into pairs is called on
+    /// a permutation with an odd length, which would leave one index without a partner.
+    #[error("The length of the permutation is {length}, which can't be split into pairs")]
+    OddLength { length: u32 },
+
+    /// This error is invoked when a table that is supposed to be a permutation of `0..length`
+    /// either repeats a value or contains a value outside of that range.
+    #[error("The values are not a permutation of 0..{length}")]
+    NotAPermutation { length: u32 },
+
+    /// This error is invoked when the caller asks to step through a range of indices with a step
+    /// of zero, which would never advance.
+    #[error("The step between indices must be nonzero")]
+    ZeroStep,
+
+    /// This error is invoked when the caller asks to split the outputs of a permutation into zero
+    /// buckets, which would leave nowhere to put them.
+    #[error("The number of buckets must be nonzero")]
+    ZeroBuckets,
 }
 
 /// A permutation result, which is simply an alias for any type that could return a permutation
 /// error.
 pub type PermutationResult<T> = Result<T, PermutationError>;
+
+/// Permutation errors are caused by bad arguments, so they convert to `InvalidInput` IO errors.
+/// This lets `?` propagate them out of functions that return an `io::Result`.
+impl From<PermutationError> for io::Error {
+    fn from(e: PermutationError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidInput, e)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_range_out_of_bounds() {
+        let e = PermutationError::RangeOutOfBounds {
+            start: 5,
+            end: 12,
+            length: 10,
+        };
+        assert_eq!(e.to_string(), "The range 5..12 is not within 0..10");
+    }
+
+    #[test]
+    fn test_into_io_error() {
+        let e = PermutationError::ShuffleOutOfRange {
+            shuffle: 12,
+            max_shuffle: 10,
+        };
+        let message = e.to_string();
+        let io_error = io::Error::from(e);
+        assert_eq!(io_error.kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(io_error.to_string(), message);
+        assert!(io_error.to_string().contains("12"));
+    }
+}
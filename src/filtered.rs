@@ -0,0 +1,187 @@
+//! A permutation that skips over a set of excluded values.
+
+use crate::error::{PermutationError, PermutationResult};
+use crate::HashedPermutation;
+use std::mem;
+
+/// A bijection from `0..len()` onto the values of `0..length` that weren't excluded.
+///
+/// This is created with [`HashedPermutation::with_exclusions`]. It yields the values in the same
+/// order as the permutation it was created from, skipping over the excluded ones. To do that, it
+/// keeps a compact remap table: the sorted list of inputs that the original permutation maps onto
+/// an excluded value. Each filtered index is mapped back to an input of the original permutation
+/// by skipping over those inputs, which is a binary search in O(log k) time for `k` exclusions.
+#[derive(Clone, Debug)]
+pub struct FilteredPermutation {
+    /// The permutation the exclusions were taken out of
+    inner: HashedPermutation,
+
+    /// The inputs of `inner` that map onto an excluded value, sorted in ascending order
+    skipped: Vec<u32>,
+
+    /// The excluded values, sorted and without any duplicates
+    excluded: Vec<u32>,
+}
+
+impl HashedPermutation {
+    /// Create a permutation over the values of `0..length` that are not in `excluded`.
+    ///
+    /// The resulting permutation is a bijection from `0..length - k` onto the remaining values,
+    /// where `k` is the number of distinct excluded values within `0..length`. Excluded values
+    /// outside of `0..length` are ignored, as are duplicates. The remaining values come out in
+    /// the same order as they do from this permutation.
+    ///
+    /// Finding the inputs that map onto the excluded values takes a pass over the permutation, so
+    /// this takes O(n log k) time in the worst case. It stops early once every excluded value has
+    /// been found.
+    pub fn with_exclusions(&self, excluded: &[u32]) -> FilteredPermutation {
+        let mut excluded: Vec<u32> = excluded
+            .iter()
+            .copied()
+            .filter(|&x| x < self.length.get())
+            .collect();
+        excluded.sort_unstable();
+        excluded.dedup();
+        excluded.shrink_to_fit();
+
+        let mut skipped = Vec::with_capacity(excluded.len());
+
+        for i in 0..self.length.get() {
+            if skipped.len() == excluded.len() {
+                break;
+            }
+            if excluded.binary_search(&self.permute(i)).is_ok() {
+                skipped.push(i);
+            }
+        }
+        FilteredPermutation {
+            inner: self.clone(),
+            skipped,
+            excluded,
+        }
+    }
+}
+
+impl FilteredPermutation {
+    /// The number of values that remain after the exclusions
+    pub fn len(&self) -> u32 {
+        self.inner.length.get() - self.excluded.len() as u32
+    }
+
+    /// Whether every value was excluded
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The excluded values, sorted in ascending order
+    pub fn excluded(&self) -> &[u32] {
+        &self.excluded
+    }
+
+    /// The number of bytes the remap table and the table of exclusions allocate on the heap
+    pub fn heap_bytes(&self) -> usize {
+        (self.skipped.capacity() + self.excluded.capacity()) * mem::size_of::<u32>()
+    }
+
+    /// Shuffle or permute a particular value.
+    ///
+    /// `input` must be within `0..len()`, and the result is never one of the excluded values.
+    pub fn shuffle(&self, input: u32) -> PermutationResult<u32> {
+        if input >= self.len() {
+            return Err(PermutationError::ShuffleOutOfRange {
+                shuffle: input,
+                max_shuffle: self.len(),
+            });
+        }
+        Ok(self.inner.permute(self.select(input)))
+    }
+
+    /// Find the `index`-th input of the original permutation (counting from zero) that isn't
+    /// skipped.
+    fn select(&self, index: u32) -> u32 {
+        // The input we're looking for is `index + k`, where `k` is the number of skipped inputs
+        // below it. Since the skipped inputs are sorted and distinct, `skipped[j] - j` is the
+        // number of remaining inputs below `skipped[j]`, which is nondecreasing, so we can binary
+        // search for `k`.
+        let (mut lo, mut hi) = (0, self.skipped.len());
+
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+
+            if self.skipped[mid] - mid as u32 <= index {
+                lo = mid + 1;
+            } else {
+                hi = mid;
+            }
+        }
+        index + lo as u32
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::lengths_and_seeds;
+    use std::collections::HashSet;
+    use std::num::NonZeroU32;
+
+    #[test]
+    // The filtered permutation should never yield an excluded value, and should yield every
+    // remaining value exactly once.
+    fn test_exclusions() {
+        let (lengths, seeds) = lengths_and_seeds();
+
+        for (&length, seed) in lengths.iter().zip(seeds) {
+            let perm = HashedPermutation::new_with_seed(length, seed);
+            let length = length.get();
+            let excluded = vec![0, 3, 3, length - 1, length / 2, length + 10];
+            let filtered = perm.with_exclusions(&excluded);
+            let excluded_set: HashSet<u32> = excluded.into_iter().filter(|&x| x < length).collect();
+            assert_eq!(filtered.len(), length - excluded_set.len() as u32);
+            assert_eq!(filtered.excluded().len(), excluded_set.len());
+
+            let mut seen = HashSet::new();
+
+            for i in 0..filtered.len() {
+                let res = filtered.shuffle(i).unwrap();
+                assert!(res < length);
+                assert!(!excluded_set.contains(&res));
+                assert!(seen.insert(res));
+            }
+            assert_eq!(seen.len() + excluded_set.len(), length as usize);
+            assert!(filtered.shuffle(filtered.len()).is_err());
+        }
+    }
+
+    #[test]
+    // The filtered permutation should yield the values of the original permutation in the same
+    // order, just without the excluded ones.
+    fn test_exclusions_keep_order() {
+        let (lengths, seeds) = lengths_and_seeds();
+
+        for (&length, seed) in lengths.iter().zip(seeds) {
+            let perm = HashedPermutation::new_with_seed(length, seed);
+            let length = length.get();
+            let excluded = vec![1, 2, length - 2, length / 3, length + 1];
+            let filtered = perm.with_exclusions(&excluded);
+            let expected: Vec<u32> = (0..length)
+                .map(|i| perm.shuffle(i).unwrap())
+                .filter(|v| !excluded.contains(v))
+                .collect();
+            let result: Vec<u32> = (0..filtered.len())
+                .map(|i| filtered.shuffle(i).unwrap())
+                .collect();
+            assert_eq!(result, expected);
+        }
+    }
+
+    #[test]
+    fn test_exclude_everything() {
+        let perm = HashedPermutation::new_with_seed(NonZeroU32::new(3).unwrap(), 0);
+        let filtered = perm.with_exclusions(&[0, 1, 2]);
+        assert!(filtered.is_empty());
+        assert_eq!(filtered.heap_bytes(), 2 * 3 * 4);
+        assert_eq!(filtered.len(), 0);
+        assert!(filtered.shuffle(0).is_err());
+    }
+}
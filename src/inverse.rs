@@ -0,0 +1,122 @@
+//! A materialized inverse of a hashed permutation.
+
+use crate::error::{PermutationError, PermutationResult};
+use crate::HashedPermutation;
+use std::mem;
+
+/// The inverse of a [`HashedPermutation`], which maps each shuffled output back to its input.
+///
+/// Kensler's permutation can't be inverted cheaply, so this precomputes the whole inverse table
+/// in O(n) time when it is created. After that, every lookup is O(1). This trades away the
+/// permutation's zero memory overhead, since the table holds `length` entries.
+#[derive(Clone, Debug)]
+pub struct InversePermutation {
+    /// `table[output]` is the input that maps to `output`
+    pub(crate) table: Vec<u32>,
+}
+
+impl HashedPermutation {
+    /// Materialize the inverse of this permutation.
+    ///
+    /// ```
+    /// # use hashed_permutation::HashedPermutation;
+    /// use std::num::NonZeroU32;
+    ///
+    /// let perm = HashedPermutation::new_with_seed(NonZeroU32::new(10).unwrap(), 1234);
+    /// let inverse = perm.inverse();
+    /// let shuffled = perm.shuffle(3).unwrap();
+    /// assert_eq!(inverse.apply(shuffled).unwrap(), 3);
+    /// ```
+    pub fn inverse(&self) -> InversePermutation {
+        let n = self.length.get();
+        let mut table = vec![0; n as usize];
+        // The writes land all over the table, so for large lengths nearly every one of them
+        // misses the cache. Shuffling a whole block of inputs before writing any of them gives the
+        // prefetches for that block time to land while the rest of it is being hashed.
+        let mut targets = [0; SCATTER_BLOCK as usize];
+
+        for start in (0..n).step_by(SCATTER_BLOCK as usize) {
+            let end = start.saturating_add(SCATTER_BLOCK).min(n);
+
+            for (target, i) in targets.iter_mut().zip(start..end) {
+                *target = self.permute(i);
+                prefetch_write(&table[*target as usize]);
+            }
+            for (&target, i) in targets.iter().zip(start..end) {
+                table[target as usize] = i;
+            }
+        }
+        InversePermutation { table }
+    }
+}
+
+/// The number of inputs that are shuffled ahead of writing them out to a scattered table
+const SCATTER_BLOCK: u32 = 16;
+
+/// Hint to the CPU that `slot` is about to be written, so it can start loading its cache line.
+///
+/// This is only a hint and never changes the result. It does nothing on architectures without a
+/// stable prefetch intrinsic.
+#[inline(always)]
+fn prefetch_write<T>(slot: &T) {
+    #[cfg(target_arch = "x86_64")]
+    // SAFETY: prefetching never faults, even for an invalid address, and SSE is always available
+    // on x86_64.
+    unsafe {
+        use std::arch::x86_64::{_mm_prefetch, _MM_HINT_T0};
+        _mm_prefetch::<_MM_HINT_T0>((slot as *const T).cast::<i8>());
+    }
+    #[cfg(not(target_arch = "x86_64"))]
+    let _ = slot;
+}
+
+impl InversePermutation {
+    /// The number of bytes the inverse table allocates on the heap
+    pub fn heap_bytes(&self) -> usize {
+        self.table.capacity() * mem::size_of::<u32>()
+    }
+
+    /// Get the input that the permutation maps to `output`.
+    ///
+    /// `output` must be within `0..length`.
+    pub fn apply(&self, output: u32) -> PermutationResult<u32> {
+        self.table
+            .get(output as usize)
+            .copied()
+            .ok_or(PermutationError::ShuffleOutOfRange {
+                shuffle: output,
+                max_shuffle: self.table.len() as u32,
+            })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::edge_lengths_and_seeds;
+    use std::num::NonZeroU32;
+
+    #[test]
+    fn test_inverse() {
+        let (lengths, seeds) = edge_lengths_and_seeds();
+
+        for (&length, seed) in lengths.iter().zip(seeds) {
+            let perm = HashedPermutation::new_with_seed(length, seed);
+            let length = length.get();
+            let inverse = perm.inverse();
+
+            for x in 0..length {
+                assert_eq!(inverse.apply(perm.shuffle(x).unwrap()).unwrap(), x);
+            }
+            assert!(inverse.apply(length).is_err());
+        }
+    }
+
+    #[test]
+    fn test_heap_bytes() {
+        for &length in &[1, 5, 13, 100, 249] {
+            let perm = HashedPermutation::new_with_seed(NonZeroU32::new(length).unwrap(), 0);
+            assert_eq!(perm.inverse().heap_bytes(), length as usize * 4);
+        }
+    }
+}
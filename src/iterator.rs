@@ -1,13 +1,42 @@
-use crate::HashedPermutation;
+use crate::{HashedPermutation, Permute};
+#[cfg(feature = "serde")]
+use std::convert::TryFrom;
+use std::iter;
 use std::num::NonZeroU32;
 
+/// An iterator over a [`HashedPermutation`], which is the most common kind of
+/// [`PermutationIter`].
+pub type HashedIter = PermutationIter<HashedPermutation>;
+
 /// An iterator that allows you to iterate over a sequence of permuted numbers with O(1) space.
-pub struct HashedIter {
+///
+/// This works with any [`Permute`] backend, and yields `shuffle(i)` for each `i` in
+/// `0..len()`. Iterators over a [`HashedPermutation`] are usually created through the
+/// [`HashedIter`] alias or [`HashedPermutation::iter`].
+///
+/// With the `serde` feature enabled, the iterator can be serialized along with its current
+/// position, so a long-running iteration can be checkpointed and resumed later.
+#[derive(Clone, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "HashedIterState<P>")
+)]
+pub struct PermutationIter<P: Permute> {
     /// The "engine" driving the permutations
-    permutation_engine: HashedPermutation,
+    permutation_engine: P,
 
     /// The current index that's being iterated on
     current_idx: u32,
+
+    /// The index to stop iterating at (exclusive)
+    end: u32,
+
+    /// The number of elements that have been yielded so far, from either end
+    consumed: u32,
+
+    /// Whether the iterator runs from the last index down to the first
+    reversed: bool,
 }
 
 /// The iterator version of the hashed permutation algorithm
@@ -32,57 +61,322 @@ impl HashedIter {
     #[cfg(feature = "use-rand")]
     pub fn new(length: NonZeroU32) -> Self {
         let permutation_engine = HashedPermutation::new(length);
+        Self::bounded(permutation_engine, length.get())
+    }
+
+    /// Create a new hashed iterator with a given length and a seed value
+    ///
+    /// If you want a random seed instead, enable the `use-rand` feature and use
+    /// `HashedIter::new`.
+    pub fn new_with_seed(length: NonZeroU32, seed: u32) -> Self {
+        let permutation_engine = HashedPermutation::new_with_seed(length, seed);
+        Self::bounded(permutation_engine, length.get())
+    }
+
+    /// Create a new hashed iterator with a given length and a seed value that runs backwards
+    ///
+    /// This yields `shuffle(length - 1)` first and `shuffle(0)` last, which is the same sequence
+    /// as [`HashedIter::new_with_seed`] in reverse. Calling `rev` on this iterator runs it forwards
+    /// again.
+    pub fn new_reversed(length: NonZeroU32, seed: u32) -> Self {
+        Self {
+            reversed: true,
+            ..Self::new_with_seed(length, seed)
+        }
+    }
+}
+
+impl<P: Permute> PermutationIter<P> {
+    /// Create an iterator over every index of an arbitrary permutation
+    ///
+    /// ```
+    /// # use hashed_permutation::{LcgPermutation, PermutationIter};
+    /// use std::num::NonZeroU32;
+    ///
+    /// let perm = LcgPermutation::new_with_seed(NonZeroU32::new(10).unwrap(), 1234);
+    /// let mut values: Vec<u32> = PermutationIter::from_permutation(perm).collect();
+    /// values.sort();
+    /// assert_eq!(values, (0..10).collect::<Vec<u32>>());
+    /// ```
+    pub fn from_permutation(permutation_engine: P) -> Self {
+        let end = permutation_engine.len();
+        Self::bounded(permutation_engine, end)
+    }
+
+    /// Create a new iterator that stops after `end` elements
+    ///
+    /// `end` must not be larger than the length of the permutation.
+    pub(crate) fn bounded(permutation_engine: P, end: u32) -> Self {
+        debug_assert!(end <= permutation_engine.len());
 
         Self {
             permutation_engine,
             current_idx: 0,
+            end,
+            consumed: 0,
+            reversed: false,
         }
     }
 
-    /// Create a new hashed iterator with a given length and a seed value
-    pub fn new_with_seed(length: NonZeroU32, seed: u32) -> Self {
-        let permutation_engine = HashedPermutation::new_with_seed(length, seed);
+    /// The number of elements the iterator has left to yield
+    pub fn remaining(&self) -> u32 {
+        self.end - self.current_idx
+    }
+
+    /// The number of elements the iterator has yielded so far, from either end
+    pub fn consumed(&self) -> u32 {
+        self.consumed
+    }
+
+    /// Drain the remaining elements into a vector, sorted in ascending order.
+    ///
+    /// For an iterator over the whole permutation this is simply `0..length`, but for a bounded
+    /// or partially consumed iterator it is the sorted subset of the values it had left. The
+    /// values are distinct, since the permutation is a bijection.
+    pub fn collect_sorted(self) -> Vec<u32> {
+        let mut values: Vec<u32> = self.collect();
+        values.sort_unstable();
+        values
+    }
+
+    /// Consume the iterator, yielding each remaining element along with the index it was
+    /// shuffled from.
+    ///
+    /// This yields `(i, shuffle(i))` pairs. Unlike `Iterator::enumerate`, the first element of
+    /// each pair is the actual pre-shuffle index rather than a running count of the elements
+    /// yielded so far, which also holds for an iterator that has already been partially consumed.
+    /// The pairs come out in the same order as the iterator would yield them, so a reversed
+    /// iterator starts from the highest remaining index.
+    pub fn enumerate_original(self) -> impl Iterator<Item = (u32, u32)> {
+        let Self {
+            permutation_engine,
+            current_idx,
+            end,
+            reversed,
+            ..
+        } = self;
+
+        (0..end - current_idx).map(move |k| {
+            let i = if reversed {
+                end - 1 - k
+            } else {
+                current_idx + k
+            };
+            (i, shuffle_in_range(&permutation_engine, i))
+        })
+    }
+
+    /// Consume the iterator, grouping runs of consecutive elements that fall in the same bucket.
+    ///
+    /// The values `0..length` are split into `buckets` contiguous ranges the same way as
+    /// [`HashedPermutation::bucket`], so a value `x` lands in bucket `x * buckets / length`. Each
+    /// item is a bucket along with the run of elements that were yielded one after another and
+    /// landed in it. Since the elements come out in shuffled order, this groups runs rather than
+    /// whole buckets: the same bucket usually shows up many times, in separate runs. The groups
+    /// are built as the iterator streams, so only the current run is held in memory.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `buckets` is zero.
+    pub fn group_by_bucket(self, buckets: u32) -> impl Iterator<Item = (u32, Vec<u32>)> {
+        assert!(
+            buckets > 0,
+            "the number of buckets must be greater than zero"
+        );
+        let n = u64::from(self.permutation_engine.len());
+        let to_bucket = move |x: u32| (u64::from(x) * u64::from(buckets) / n) as u32;
+        let mut elements = self.peekable();
+
+        iter::from_fn(move || {
+            let first = elements.next()?;
+            let bucket = to_bucket(first);
+            let mut members = vec![first];
+
+            while let Some(x) = elements.next_if(|&x| to_bucket(x) == bucket) {
+                members.push(x);
+            }
+            Some((bucket, members))
+        })
+    }
+
+    /// Shuffle the lowest index that hasn't been visited yet
+    fn take_front(&mut self) -> Option<u32> {
+        if self.current_idx >= self.end {
+            return None;
+        }
+        let elem = shuffle_in_range(&self.permutation_engine, self.current_idx);
+        self.current_idx += 1;
+        self.consumed += 1;
+        Some(elem)
+    }
+
+    /// Shuffle the highest index that hasn't been visited yet
+    fn take_back(&mut self) -> Option<u32> {
+        if self.current_idx >= self.end {
+            return None;
+        }
+        self.end -= 1;
+        self.consumed += 1;
+        Some(shuffle_in_range(&self.permutation_engine, self.end))
+    }
+}
+
+/// Shuffle an index that is known to be within `0..len()`
+fn shuffle_in_range<P: Permute>(permutation_engine: &P, input: u32) -> u32 {
+    permutation_engine
+        .shuffle(input)
+        .expect("the iterator only visits indices within the permutation")
+}
+
+/// The raw state of a `PermutationIter`, which is validated before it becomes an iterator again
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct HashedIterState<P> {
+    permutation_engine: P,
+    current_idx: u32,
+    end: u32,
+    #[serde(default)]
+    consumed: u32,
+    #[serde(default)]
+    reversed: bool,
+}
+
+#[cfg(feature = "serde")]
+impl<P: Permute> TryFrom<HashedIterState<P>> for PermutationIter<P> {
+    type Error = String;
+
+    fn try_from(state: HashedIterState<P>) -> Result<Self, Self::Error> {
+        let length = state.permutation_engine.len();
+
+        if state.end > length {
+            return Err(format!(
+                "the end of the iterator ({}) is past the length of the permutation ({})",
+                state.end, length
+            ));
+        }
+        if state.current_idx > state.end {
+            return Err(format!(
+                "the cursor of the iterator ({}) is past its end ({})",
+                state.current_idx, state.end
+            ));
+        }
+        Ok(Self {
+            permutation_engine: state.permutation_engine,
+            current_idx: state.current_idx,
+            end: state.end,
+            consumed: state.consumed,
+            reversed: state.reversed,
+        })
+    }
+}
+
+impl<P: Permute> Iterator for PermutationIter<P> {
+    type Item = u32;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.reversed {
+            self.take_back()
+        } else {
+            self.take_front()
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.end.saturating_sub(self.current_idx) as usize;
+        (remaining, Some(remaining))
+    }
+}
+
+impl<P: Permute> DoubleEndedIterator for PermutationIter<P> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.reversed {
+            self.take_front()
+        } else {
+            self.take_back()
+        }
+    }
+}
+
+// The size hint is exact, since we know exactly how many indices are left to shuffle
+impl<P: Permute> ExactSizeIterator for PermutationIter<P> {}
+
+/// An iterator over the permuted values of a contiguous sub-range of inputs.
+///
+/// This yields `shuffle(i)` for each `i` in `start..end`, in order. It can also be iterated from
+/// the back, and always knows exactly how many elements are left.
+#[derive(Clone, Debug)]
+pub struct HashedRangeIter {
+    /// The "engine" driving the permutations
+    permutation_engine: HashedPermutation,
+
+    /// The first input of the range the iterator was created with
+    start: u32,
+
+    /// The next input to shuffle from the front
+    current: u32,
+
+    /// The input to stop iterating at (exclusive), which moves down when iterating from the back
+    end: u32,
+}
+
+impl HashedRangeIter {
+    /// Create an iterator over the shuffles of the inputs in `start..end`
+    ///
+    /// The range must be within `0..length`.
+    pub(crate) fn new(permutation_engine: HashedPermutation, start: u32, end: u32) -> Self {
+        debug_assert!(start <= end && end <= permutation_engine.length.get());
 
         Self {
             permutation_engine,
-            current_idx: 0,
+            start,
+            current: start,
+            end,
         }
     }
+
+    /// The first input of the range this iterator was created with
+    pub fn start(&self) -> u32 {
+        self.start
+    }
 }
 
-impl Iterator for HashedIter {
+impl Iterator for HashedRangeIter {
     type Item = u32;
 
     fn next(&mut self) -> Option<Self::Item> {
-        match self.permutation_engine.shuffle(self.current_idx) {
-            Ok(elem) => {
-                self.current_idx += 1;
-                Some(elem)
-            }
-            Err(_) => None,
+        if self.current >= self.end {
+            return None;
+        }
+        let elem = self.permutation_engine.permute(self.current);
+        self.current += 1;
+        Some(elem)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.end.saturating_sub(self.current) as usize;
+        (remaining, Some(remaining))
+    }
+}
+
+impl DoubleEndedIterator for HashedRangeIter {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.current >= self.end {
+            return None;
         }
+        self.end -= 1;
+        Some(self.permutation_engine.permute(self.end))
     }
 }
 
+// The size hint is exact, since we know exactly how many indices are left to shuffle
+impl ExactSizeIterator for HashedRangeIter {}
+
 #[cfg(test)]
 mod test {
     use super::*;
-    use std::collections::HashSet;
-
-    /// A convenient helper method that returns a pair of lengths and seeds (in that order).
-    ///
-    /// This method defines the lengths and the seeds for the test cases, since these are reused
-    /// in the tests, and it's best practice to consolidate them in one place so code is not
-    /// repeated.
-    fn lengths_and_seeds() -> (Vec<NonZeroU32>, Vec<u32>) {
-        let lengths: Vec<NonZeroU32> = vec![100, 5, 13, 128, 249]
-            .iter()
-            .map(|&x| NonZeroU32::new(x).unwrap())
-            .collect();
-        let seeds = vec![100, 5, 13, 128, 249];
-        assert_eq!(lengths.len(), seeds.len());
-        (lengths, seeds)
-    }
+    use crate::test_utils::lengths_and_seeds;
+    use crate::LcgPermutation;
+    use std::collections::{BTreeSet, HashSet};
 
     #[test]
     // This method checks to see that a permutation does not have any collisions and that every
@@ -110,4 +404,277 @@ mod test {
             assert_eq!(expected, result);
         }
     }
+
+    #[test]
+    // The generic iterator should drive a non-Kensler backend through the same code paths.
+    fn test_generic_backend() {
+        let (lengths, seeds) = lengths_and_seeds();
+
+        for (&length, seed) in lengths.iter().zip(seeds) {
+            let perm = LcgPermutation::new_with_seed(length, seed);
+            let it = PermutationIter::from_permutation(perm.clone());
+            assert_eq!(it.len(), length.get() as usize);
+
+            let expected: Vec<u32> = (0..length.get())
+                .map(|i| perm.shuffle(i).unwrap())
+                .collect();
+            let forward: Vec<u32> = it.clone().collect();
+            assert_eq!(expected, forward);
+
+            let mut backward: Vec<u32> = it.clone().rev().collect();
+            backward.reverse();
+            assert_eq!(expected, backward);
+            assert_eq!(it.collect_sorted(), (0..length.get()).collect::<Vec<u32>>());
+        }
+    }
+
+    #[test]
+    // Iterating backwards should give the same elements as iterating forwards, in reverse order,
+    // and adapters that rely on the exact length should report the right indices.
+    fn test_double_ended() {
+        let (lengths, seeds) = lengths_and_seeds();
+
+        for (&length, seed) in lengths.iter().zip(seeds) {
+            let forward: Vec<u32> = HashedIter::new_with_seed(length, seed).collect();
+            let mut backward: Vec<u32> = HashedIter::new_with_seed(length, seed).rev().collect();
+            backward.reverse();
+            assert_eq!(forward, backward);
+
+            let it = HashedIter::new_with_seed(length, seed);
+            assert_eq!(it.len(), length.get() as usize);
+
+            for (i, elem) in HashedIter::new_with_seed(length, seed).enumerate() {
+                assert_eq!(forward[i], elem);
+            }
+            for (i, elem) in HashedIter::new_with_seed(length, seed).rev().enumerate() {
+                assert_eq!(forward[forward.len() - 1 - i], elem);
+            }
+
+            let last = *forward.last().unwrap();
+            let pos = HashedIter::new_with_seed(length, seed).rposition(|x| x == last);
+            assert_eq!(pos, Some(forward.len() - 1));
+        }
+    }
+
+    #[test]
+    // Consuming from both ends should meet in the middle without repeating any element.
+    fn test_meet_in_the_middle() {
+        let length = NonZeroU32::new(13).unwrap();
+        let mut it = HashedIter::new_with_seed(length, 5);
+        let mut seen = HashSet::new();
+
+        while let Some(front) = it.next() {
+            assert!(seen.insert(front));
+            assert_eq!(it.len(), it.clone().count());
+
+            if let Some(back) = it.next_back() {
+                assert!(seen.insert(back));
+            }
+        }
+        assert_eq!(seen.len(), length.get() as usize);
+        assert_eq!(it.len(), 0);
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    // Serializing an iterator partway through and deserializing it should resume the exact
+    // remaining sequence.
+    fn test_serde_round_trip() {
+        let (lengths, seeds) = lengths_and_seeds();
+
+        for (&length, seed) in lengths.iter().zip(seeds) {
+            let mut it = HashedIter::new_with_seed(length, seed);
+
+            for _ in 0..length.get() / 2 {
+                it.next();
+            }
+            let serialized = serde_json::to_string(&it).unwrap();
+            let resumed: HashedIter = serde_json::from_str(&serialized).unwrap();
+            assert_eq!(resumed.consumed(), it.consumed());
+            let expected: Vec<u32> = it.collect();
+            let actual: Vec<u32> = resumed.collect();
+            assert_eq!(expected, actual);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_serde_invalid_cursor() {
+        let json = r#"{
+            "permutation_engine": { "seed": 0, "length": 10 },
+            "current_idx": 11,
+            "end": 10
+        }"#;
+        assert!(serde_json::from_str::<HashedIter>(json).is_err());
+
+        let json = r#"{
+            "permutation_engine": { "seed": 0, "length": 10 },
+            "current_idx": 0,
+            "end": 11
+        }"#;
+        assert!(serde_json::from_str::<HashedIter>(json).is_err());
+    }
+
+    #[test]
+    fn test_enumerate_original() {
+        let (lengths, seeds) = lengths_and_seeds();
+
+        for (&length, seed) in lengths.iter().zip(seeds) {
+            let perm = HashedPermutation::new_with_seed(length, seed);
+            let pairs: Vec<(u32, u32)> = perm.iter().enumerate_original().collect();
+            assert_eq!(pairs.len(), length.get() as usize);
+
+            for (i, &(original, shuffled)) in pairs.iter().enumerate() {
+                assert_eq!(original, i as u32);
+                assert_eq!(shuffled, perm.shuffle(original).unwrap());
+            }
+
+            // A partially consumed iterator should still report the true original indices
+            let mut it = perm.iter();
+            it.next();
+            let (original, shuffled) = it.enumerate_original().next().unwrap();
+            assert_eq!(original, 1);
+            assert_eq!(shuffled, perm.shuffle(1).unwrap());
+        }
+    }
+
+    #[test]
+    // A range iterator should yield exactly the shuffles of its range, in order, from either end.
+    fn test_range_iter() {
+        let (lengths, seeds) = lengths_and_seeds();
+
+        for (&length, seed) in lengths.iter().zip(seeds) {
+            let perm = HashedPermutation::new_with_seed(length, seed);
+            let n = length.get();
+
+            for &(start, end) in &[(0, n), (0, 0), (n / 3, n / 2), (n - 1, n), (n, n)] {
+                let it = perm.range_iter(start, end).unwrap();
+                assert_eq!(it.start(), start);
+                assert_eq!(it.len() as u32, end - start);
+
+                let expected: Vec<u32> = (start..end).map(|i| perm.shuffle(i).unwrap()).collect();
+                let actual: Vec<u32> = it.clone().collect();
+                assert_eq!(expected, actual);
+
+                let mut backward: Vec<u32> = it.rev().collect();
+                backward.reverse();
+                assert_eq!(expected, backward);
+            }
+        }
+    }
+
+    #[test]
+    fn test_range_iter_invalid() {
+        let perm = HashedPermutation::new_with_seed(NonZeroU32::new(10).unwrap(), 0);
+        assert!(perm.range_iter(0, 11).is_err());
+        assert!(perm.range_iter(5, 4).is_err());
+        assert!(perm.range_iter(11, 12).is_err());
+    }
+
+    #[test]
+    fn test_new_reversed() {
+        let (lengths, seeds) = lengths_and_seeds();
+
+        for (&length, seed) in lengths.iter().zip(seeds) {
+            let mut expected: Vec<u32> = HashedIter::new_with_seed(length, seed).collect();
+            expected.reverse();
+            let reversed: Vec<u32> = HashedIter::new_reversed(length, seed).collect();
+            assert_eq!(expected, reversed);
+
+            // Running the reversed iterator backwards should be the forward order again
+            let mut forward: Vec<u32> = HashedIter::new_reversed(length, seed).rev().collect();
+            forward.reverse();
+            assert_eq!(expected, forward);
+
+            let pairs: Vec<(u32, u32)> = HashedIter::new_reversed(length, seed)
+                .enumerate_original()
+                .collect();
+            assert_eq!(pairs[0].0, length.get() - 1);
+            assert!(pairs.into_iter().map(|(_, x)| x).eq(reversed));
+        }
+    }
+
+    #[test]
+    fn test_remaining_consumed() {
+        let length = 50;
+        let mut iter = HashedIter::new_with_seed(NonZeroU32::new(length).unwrap(), 1234);
+
+        for k in 0..length {
+            assert_eq!(iter.consumed(), k);
+            assert_eq!(iter.remaining(), length - k);
+            assert_eq!(iter.remaining() as usize, iter.len());
+            iter.next();
+        }
+        assert_eq!(iter.consumed(), length);
+        assert_eq!(iter.remaining(), 0);
+
+        // Taking from the back counts as well
+        let mut iter = HashedIter::new_with_seed(NonZeroU32::new(length).unwrap(), 1234);
+        iter.by_ref().take(3).for_each(drop);
+        iter.by_ref().rev().take(4).for_each(drop);
+        assert_eq!(iter.consumed(), 7);
+        assert_eq!(iter.remaining(), length - 7);
+
+        // A bounded iterator only counts what it yields, not the indices it leaves out
+        let perm = HashedPermutation::new_with_seed(NonZeroU32::new(100).unwrap(), 1234);
+        let mut iter = perm.take_iter(40);
+
+        for k in 0..40 {
+            assert_eq!(iter.consumed(), k);
+            assert_eq!(iter.remaining(), 40 - k);
+            iter.next();
+        }
+        assert_eq!(iter.consumed(), 40);
+        assert_eq!(iter.remaining(), 0);
+    }
+
+    #[test]
+    // Flattening the runs should give back the whole permutation, and every run should be a
+    // maximal stretch of elements in the same bucket.
+    fn test_group_by_bucket() {
+        let (lengths, seeds) = lengths_and_seeds();
+
+        for (&length, seed) in lengths.iter().zip(seeds) {
+            let perm = HashedPermutation::new_with_seed(length, seed);
+            let inverse = perm.inverse();
+
+            for &buckets in &[1, 4, 7, length.get()] {
+                let groups: Vec<(u32, Vec<u32>)> = perm.iter().group_by_bucket(buckets).collect();
+                let flattened: Vec<u32> = groups.iter().flat_map(|(_, m)| m.clone()).collect();
+                assert!(flattened.into_iter().eq(perm.iter()));
+
+                for (bucket, members) in &groups {
+                    assert!(!members.is_empty());
+                    assert!(*bucket < buckets);
+
+                    for &x in members {
+                        let input = inverse.apply(x).unwrap();
+                        assert_eq!(perm.bucket(input, buckets).unwrap(), *bucket);
+                    }
+                }
+                assert!(groups.windows(2).all(|w| w[0].0 != w[1].0));
+            }
+        }
+        assert_eq!(
+            HashedIter::new_with_seed(NonZeroU32::new(10).unwrap(), 0)
+                .group_by_bucket(1)
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_collect_sorted() {
+        let perm = HashedPermutation::new_with_seed(NonZeroU32::new(100).unwrap(), 1234);
+        let mut iter = perm.take_iter(40);
+        iter.by_ref().take(5).for_each(drop);
+        let expected: BTreeSet<u32> = iter.clone().collect();
+        let sorted = iter.collect_sorted();
+        assert_eq!(sorted.len(), 35);
+        assert!(sorted.windows(2).all(|w| w[0] < w[1]));
+        assert!(sorted.iter().eq(expected.iter()));
+
+        let full = HashedIter::new_with_seed(NonZeroU32::new(100).unwrap(), 1234);
+        assert_eq!(full.collect_sorted(), (0..100).collect::<Vec<u32>>());
+    }
 }
@@ -4,10 +4,12 @@
 //! [paper](https://graphics.pixar.com/library/MultiJitteredSampling/paper.pdf)
 //! on correlated multi-jittered sampling.
 
+use crate::domain::mix32;
 use crate::error::{PermutationError, PermutationResult};
-#[cfg(feature = "use-rand")]
-use rand::prelude::*;
-use std::num::{NonZeroU32, Wrapping};
+use crate::{HashedIter, HashedRangeIter};
+use std::convert::TryFrom;
+use std::iter::{self, FromIterator};
+use std::num::{NonZeroU16, NonZeroU32, Wrapping};
 
 /// The `HashedPermutation` struct stores the initial `seed` and `length` of the permutation
 /// vector. In other words, if you want to shuffle the numbers from `0..n`, then `length = n`.
@@ -15,6 +17,7 @@ use std::num::{NonZeroU32, Wrapping};
 /// Because the shuffle is performed using bit arithmetic, the fields have to be 32 bit integers.
 /// Unfortunately, larger types are not supported at this time.
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct HashedPermutation {
     /// The random seed that dictates which permutation you want to use. The shuffle is
     /// deterministic, so using the same seed will yield the same permutation every time.
@@ -26,6 +29,12 @@ pub struct HashedPermutation {
 }
 
 impl HashedPermutation {
+    /// The largest length for which [`HashedPermutation::new_checked`] verifies the bijection.
+    ///
+    /// The check is O(n) in both time and memory, so it is skipped for anything longer than this
+    /// to avoid slowing down debug builds.
+    pub const BIJECTION_CHECK_MAX_LENGTH: u32 = 1 << 16;
+
     /// Create a new instance of the hashed permutation with a random seed.
     ///
     /// This method creates a hashed permutation of some length and initializes the seed to some
@@ -37,18 +46,439 @@ impl HashedPermutation {
         HashedPermutation { length, seed }
     }
 
+    /// Create a new instance of the hashed permutation with a random seed.
+    ///
+    /// This reads the seed straight from the operating system with `getrandom`, without pulling in
+    /// the rest of `rand`. If the `use-rand` feature is also enabled, that version of this
+    /// constructor takes precedence.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the operating system fails to provide random bytes.
+    #[cfg(all(feature = "getrandom", not(feature = "use-rand")))]
+    pub fn new(length: NonZeroU32) -> Self {
+        let mut bytes = [0; 4];
+
+        if let Err(e) = getrandom::getrandom(&mut bytes) {
+            panic!("failed to generate a random seed: {}", e);
+        }
+        HashedPermutation {
+            length,
+            seed: u32::from_le_bytes(bytes),
+        }
+    }
+
     /// Create a new instance of the hashed permutation given a length and seed
+    ///
+    /// If you want a random seed instead, enable the `use-rand` or `getrandom` feature and use
+    /// `HashedPermutation::new`.
+    #[cfg_attr(
+        not(any(feature = "use-rand", feature = "getrandom")),
+        doc = r#"
+The `use-rand` and `getrandom` features are currently disabled, so `HashedPermutation::new` does
+not exist:
+
+```compile_fail
+# use hashed_permutation::HashedPermutation;
+use std::num::NonZeroU32;
+
+let perm = HashedPermutation::new(NonZeroU32::new(10).unwrap());
+```
+"#
+    )]
     pub fn new_with_seed(length: NonZeroU32, seed: u32) -> Self {
         HashedPermutation { seed, length }
     }
 
+    /// Create a new instance of the hashed permutation, getting the seed from a closure.
+    ///
+    /// `f` is called exactly once, and its result becomes the seed. This lets generic code plug in
+    /// any source of seeds, such as a counter or a hash of an environment variable.
+    pub fn with_seed_fn<F: FnOnce() -> u32>(length: NonZeroU32, f: F) -> Self {
+        Self::new_with_seed(length, f())
+    }
+
+    /// Create a new instance of the hashed permutation from a seed and a plain `u32` length.
+    ///
+    /// Note that the seed comes first here, unlike [`HashedPermutation::new_with_seed`]. This is
+    /// meant for lengths that come from untrusted sources such as config files, so rather than
+    /// requiring a `NonZeroU32`, it returns an error if `length` is zero.
+    pub fn from_parts(seed: u32, length: u32) -> PermutationResult<Self> {
+        let length = NonZeroU32::new(length).ok_or(PermutationError::ZeroLength)?;
+        Ok(Self::new_with_seed(length, seed))
+    }
+
+    /// Create a copy of this permutation with the same seed, but a different length.
+    pub fn with_length(&self, length: NonZeroU32) -> HashedPermutation {
+        HashedPermutation {
+            length,
+            ..self.clone()
+        }
+    }
+
+    /// Create a new instance of the hashed permutation given a length and seed, rejecting lengths
+    /// larger than `max`.
+    ///
+    /// This is useful for guarding against pathological inputs, for example when the length comes
+    /// from an untrusted source and the permutation will later be materialized.
+    pub fn new_bounded(length: NonZeroU32, seed: u32, max: u32) -> PermutationResult<Self> {
+        if length.get() > max {
+            return Err(PermutationError::LengthTooLarge {
+                length: length.get(),
+                max,
+            });
+        }
+        Ok(Self::new_with_seed(length, seed))
+    }
+
+    /// Create a new instance of the hashed permutation over the inclusive range `0..=end`.
+    ///
+    /// This is equivalent to calling [`HashedPermutation::new_with_seed`] with a length of
+    /// `end + 1`, and returns an error if that length overflows a `u32`.
+    pub fn from_inclusive(end: u32, seed: u32) -> PermutationResult<Self> {
+        let length = end
+            .checked_add(1)
+            .and_then(NonZeroU32::new)
+            .ok_or(PermutationError::LengthOverflow { end })?;
+        Ok(Self::new_with_seed(length, seed))
+    }
+
+    /// Search for a permutation that satisfies a set of `(input, output)` constraints.
+    ///
+    /// This tries the seeds `0..max_tries` in order and returns the first permutation where
+    /// `shuffle(input) == output` for every constraint, or `None` if no seed in that range
+    /// satisfies all of them. This is a brute force search, so it is mostly useful for generating
+    /// test fixtures with a handful of constraints.
+    pub fn find_seed(
+        length: NonZeroU32,
+        constraints: &[(u32, u32)],
+        max_tries: u32,
+    ) -> Option<HashedPermutation> {
+        (0..max_tries)
+            .map(|seed| Self::new_with_seed(length, seed))
+            .find(|perm| {
+                constraints
+                    .iter()
+                    .all(|&(input, output)| perm.shuffle(input).ok() == Some(output))
+            })
+    }
+
+    /// Find the smallest seed whose permutation maps `input` to `target`.
+    ///
+    /// This tries the seeds `0..=max_seed` in ascending order, like
+    /// [`HashedPermutation::find_seed`] with a single constraint, and returns `None` if none of
+    /// them work. That includes the case where `input` or `target` isn't within `0..length`.
+    pub fn smallest_seed_mapping(
+        length: NonZeroU32,
+        input: u32,
+        target: u32,
+        max_seed: u32,
+    ) -> Option<u32> {
+        (0..=max_seed)
+            .find(|&seed| Self::new_with_seed(length, seed).shuffle(input).ok() == Some(target))
+    }
+
+    /// Follow a single index across a stream of seeds.
+    ///
+    /// This yields `(seed, shuffle(index))` for each seed, using the permutation over `0..length`
+    /// with that seed, which shows how the index moves as the seed changes. If `index` isn't
+    /// within `0..length`, every item holds the error instead. The stream is lazy, so `seeds` may
+    /// be endless.
+    pub fn seed_sweep(
+        length: NonZeroU32,
+        index: u32,
+        seeds: impl IntoIterator<Item = u32>,
+    ) -> impl Iterator<Item = (u32, PermutationResult<u32>)> {
+        seeds
+            .into_iter()
+            .map(move |seed| (seed, Self::new_with_seed(length, seed).shuffle(index)))
+    }
+
+    /// Create an endless stream of permutations over `0..length`, one for each epoch of an online
+    /// algorithm.
+    ///
+    /// The `k`-th permutation's seed is `start_seed + k * 0x9e3779b9` (with wrapping arithmetic),
+    /// passed through an integer hash so that consecutive seeds are decorrelated. The stream only
+    /// repeats after 2^32 permutations.
+    pub fn seeds_iter(start_seed: u32, length: NonZeroU32) -> impl Iterator<Item = Self> {
+        iter::successors(Some(start_seed), |seed| {
+            Some(seed.wrapping_add(0x9e37_79b9))
+        })
+        .map(move |seed| Self::new_with_seed(length, mix32(seed)))
+    }
+
+    /// Derive a new permutation from this one, for chaining the stages of a pipeline.
+    ///
+    /// The child has the same length, and its seed is `mix32(seed + 0x9e3779b9)` (with wrapping
+    /// arithmetic), where `mix32` is an invertible integer hash. The child therefore only depends
+    /// on this permutation, and distinct seeds always yield distinct child seeds.
+    pub fn derive_child(&self) -> Self {
+        Self::new_with_seed(self.length, mix32(self.seed.wrapping_add(0x9e37_79b9)))
+    }
+
+    /// Create a new instance of the hashed permutation given a length and seed, verifying that it
+    /// is a bijection.
+    ///
+    /// The check only runs in debug builds, and only when `length` is at most
+    /// [`HashedPermutation::BIJECTION_CHECK_MAX_LENGTH`]. Otherwise this is identical to
+    /// [`HashedPermutation::new_with_seed`], wrapped in `Ok`.
+    ///
+    /// If the check runs and the permutation is not a bijection over `0..length`, this returns
+    /// [`PermutationError::NotAPermutation`].
+    pub fn new_checked(length: NonZeroU32, seed: u32) -> PermutationResult<Self> {
+        let perm = Self::new_with_seed(length, seed);
+
+        if Self::checks_bijection(length) {
+            Self::check_bijection(perm.unchecked_iter(), length)?;
+        }
+        Ok(perm)
+    }
+
+    /// Whether [`HashedPermutation::new_checked`] verifies the bijection for a given length.
+    fn checks_bijection(length: NonZeroU32) -> bool {
+        cfg!(debug_assertions) && length.get() <= Self::BIJECTION_CHECK_MAX_LENGTH
+    }
+
+    /// Check that the shuffled `values` hit every index in `0..length` exactly once.
+    fn check_bijection(
+        values: impl IntoIterator<Item = u32>,
+        length: NonZeroU32,
+    ) -> PermutationResult<()> {
+        if !is_permutation(values, length.get()) {
+            return Err(PermutationError::NotAPermutation {
+                length: length.get(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Create an iterator over every permuted index, from `shuffle(0)` to `shuffle(length - 1)`.
+    pub fn iter(&self) -> HashedIter {
+        HashedIter::bounded(self.clone(), self.length.get())
+    }
+
+    /// Iterate over every permuted index without any per-element validation.
+    ///
+    /// This yields the same values as [`HashedPermutation::iter`], but it borrows the permutation
+    /// instead of cloning it and maps over the input range directly, so every input is in range by
+    /// construction and there is no `Result` to unwrap.
+    pub fn unchecked_iter(&self) -> impl Iterator<Item = u32> + '_ {
+        (0..self.length.get()).map(move |i| self.permute(i))
+    }
+
+    /// Iterate over every permuted index through a boxed trait object.
+    ///
+    /// This yields the same values as [`HashedPermutation::iter`], for callers that store
+    /// iterators of different types side by side and need to erase their types.
+    pub fn boxed_iter(&self) -> Box<dyn Iterator<Item = u32> + '_> {
+        Box::new(self.unchecked_iter())
+    }
+
+    /// Collect every permuted index into any collection that implements `FromIterator`.
+    ///
+    /// The elements are inserted in permuted order, so this is equivalent to
+    /// `self.iter().collect()`.
+    pub fn collect_into<B: FromIterator<u32>>(&self) -> B {
+        self.iter().collect()
+    }
+
+    /// Find the first permuted index that satisfies `pred`.
+    ///
+    /// This visits `shuffle(0), shuffle(1), ...` in order and returns the first value for which
+    /// `pred` returns `true`, without shuffling any of the remaining indices. It returns `None` if
+    /// no value in the permutation satisfies `pred`. This is equivalent to
+    /// `self.iter().find(|&x| pred(x))`.
+    pub fn find_permuted<P: Fn(u32) -> bool>(&self, pred: P) -> Option<u32> {
+        self.unchecked_iter().find(|&output| pred(output))
+    }
+
+    /// Materialize the permutation as two parallel vectors of inputs and outputs.
+    ///
+    /// The first vector holds the inputs `0..length` in order, and the second holds their
+    /// shuffles, so `outputs[i]` is `shuffle(inputs[i])`.
+    pub fn unzip(&self) -> (Vec<u32>, Vec<u32>) {
+        (0..self.length.get()).zip(self.unchecked_iter()).unzip()
+    }
+
+    /// Get the permutation as a sparse permutation matrix.
+    ///
+    /// Each entry is the `(row, col)` coordinate of a one in the matrix, where
+    /// `col = shuffle(row)`. The entries are sorted by row, and every row and every column holds
+    /// exactly one entry.
+    pub fn to_sparse_matrix(&self) -> Vec<(u32, u32)> {
+        (0..self.length.get()).zip(self.unchecked_iter()).collect()
+    }
+
+    /// Create an iterator over the first `n` permuted indices.
+    ///
+    /// This lazily yields `shuffle(0), shuffle(1), ...` and stops after `n` elements, or once
+    /// every index in `0..length` has been visited, whichever comes first.
+    pub fn take_iter(&self, n: u32) -> HashedIter {
+        HashedIter::bounded(self.clone(), n.min(self.length.get()))
+    }
+
+    /// Split the inputs of the permutation at `mid`, returning iterators over the shuffles of
+    /// `0..mid` and `mid..length`.
+    ///
+    /// Chaining the two iterators reproduces the full permutation, so this is handy for handing
+    /// each half of the permutation to a different consumer. `mid` must be at most `length`.
+    pub fn split_at(&self, mid: u32) -> PermutationResult<(HashedRangeIter, HashedRangeIter)> {
+        let n = self.length.get();
+        Ok((self.range_iter(0, mid)?, self.range_iter(mid, n)?))
+    }
+
+    /// Get the shuffles of the inputs in `0..k`, sorted in ascending order.
+    ///
+    /// This is the set of the first `k` permuted indices, which is handy for building an index
+    /// that can be binary searched. The values are distinct, since the permutation is a bijection.
+    /// `k` must be at most `length`.
+    pub fn first_k_sorted(&self, k: u32) -> PermutationResult<Vec<u32>> {
+        let mut values: Vec<u32> = self.range_iter(0, k)?.collect();
+        values.sort_unstable();
+        Ok(values)
+    }
+
+    /// Create an iterator over the shuffles of the inputs in `start..end`.
+    ///
+    /// This yields `shuffle(start), shuffle(start + 1), ..., shuffle(end - 1)`. The range must
+    /// satisfy `start <= end <= length`.
+    pub fn range_iter(&self, start: u32, end: u32) -> PermutationResult<HashedRangeIter> {
+        self.validate_range(start, end)?;
+        Ok(HashedRangeIter::new(self.clone(), start, end))
+    }
+
+    /// Create an iterator over the shuffles of the inputs in `start..end`, in reverse.
+    ///
+    /// This yields `shuffle(end - 1), shuffle(end - 2), ..., shuffle(start)`, which is the
+    /// [`HashedPermutation::range_iter`] sequence backwards, without collecting it first. The range
+    /// must satisfy `start <= end <= length`.
+    pub fn range_iter_rev(
+        &self,
+        start: u32,
+        end: u32,
+    ) -> PermutationResult<iter::Rev<HashedRangeIter>> {
+        Ok(self.range_iter(start, end)?.rev())
+    }
+
+    /// Create an iterator over the shuffles of every `step`-th input in `start..end`.
+    ///
+    /// This yields `shuffle(start), shuffle(start + step), shuffle(start + 2 * step), ...`, for as
+    /// long as the input stays below `end`. Running this with each offset `start..start + step`
+    /// covers the whole range in disjoint stripes. The range must satisfy
+    /// `start <= end <= length`, and `step` must be nonzero.
+    pub fn range_step_iter(
+        &self,
+        start: u32,
+        end: u32,
+        step: u32,
+    ) -> PermutationResult<impl Iterator<Item = u32>> {
+        self.validate_range(start, end)?;
+
+        if step == 0 {
+            return Err(PermutationError::ZeroStep);
+        }
+        let perm = self.clone();
+        Ok((start..end)
+            .step_by(step as usize)
+            .map(move |i| perm.permute(i)))
+    }
+
+    /// Check that `start..end` is a valid range of indices for this permutation.
+    ///
+    /// A range is valid if `start <= end <= length`, in which case every index in it can be
+    /// shuffled without any further checks. This is the check that the range-based methods, such
+    /// as [`HashedPermutation::range_iter`], run up front.
+    pub fn validate_range(&self, start: u32, end: u32) -> PermutationResult<()> {
+        let length = self.length.get();
+
+        if start > end || end > length {
+            return Err(PermutationError::RangeOutOfBounds { start, end, length });
+        }
+        Ok(())
+    }
+
+    /// Create an iterator over the shuffled values that land in `lo..hi`.
+    ///
+    /// This walks the whole permutation in order and only yields the outputs within `lo..hi`, so
+    /// splitting `0..length` into disjoint output ranges shards the permutation by value. The range
+    /// must satisfy `lo <= hi <= length`.
+    pub fn outputs_in_range(
+        &self,
+        lo: u32,
+        hi: u32,
+    ) -> PermutationResult<impl Iterator<Item = u32> + '_> {
+        self.validate_range(lo, hi)?;
+        Ok(self.unchecked_iter().filter(move |x| (lo..hi).contains(x)))
+    }
+
+    /// Create an iterator that visits the inputs in a strided order before shuffling them.
+    ///
+    /// The inputs are visited as `0, stride, 2 * stride, ...`, then `1, stride + 1, ...`, and so
+    /// on until every input in `0..length` has been visited exactly once, so the iterator yields
+    /// every permuted index exactly once for any stride.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `stride` is zero.
+    pub fn interleaved_iter(&self, stride: u32) -> impl Iterator<Item = u32> + '_ {
+        assert!(stride > 0, "the stride must be greater than zero");
+        let n = self.length.get();
+
+        (0..stride.min(n))
+            .flat_map(move |offset| (offset..n).step_by(stride as usize))
+            .map(move |i| self.permute(i))
+    }
+
+    /// Create an iterator over consecutive pairs of permuted indices.
+    ///
+    /// This yields `(shuffle(0), shuffle(1))`, `(shuffle(2), shuffle(3))`, and so on. If the length
+    /// is odd, the last permuted index, `shuffle(length - 1)`, has no partner and is dropped.
+    pub fn pairs_iter(&self) -> impl Iterator<Item = (u32, u32)> + '_ {
+        (0..self.length.get() / 2).map(move |k| (self.permute(2 * k), self.permute(2 * k + 1)))
+    }
+
+    /// Split the permutation into disjoint, unordered pairs.
+    ///
+    /// This takes the permuted indices two at a time, like [`HashedPermutation::pairs_iter`], but
+    /// orders each pair so the smaller index comes first. That way a pair is the same regardless
+    /// of which of its elements was shuffled first. Every index appears in exactly one pair, so
+    /// the length must be even.
+    pub fn shuffle_pair_symmetric(&self) -> PermutationResult<Vec<(u32, u32)>> {
+        let n = self.length.get();
+
+        if n & 1 != 0 {
+            return Err(PermutationError::OddLength { length: n });
+        }
+        Ok(self
+            .pairs_iter()
+            .map(|(a, b)| (a.min(b), a.max(b)))
+            .collect())
+    }
+
+    /// Get the permutation as a plain function, for use in functional pipelines such as `map`.
+    ///
+    /// ```
+    /// # use hashed_permutation::HashedPermutation;
+    /// use std::num::NonZeroU32;
+    ///
+    /// let perm = HashedPermutation::new_with_seed(NonZeroU32::new(10).unwrap(), 1234);
+    /// let shuffled: Vec<u32> = (0..10).map(perm.as_fn()).collect();
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// The returned closure panics if it is called with an input that is not within `0..length`.
+    pub fn as_fn(&self) -> impl Fn(u32) -> u32 + '_ {
+        move |input| match self.shuffle(input) {
+            Ok(res) => res,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
     /// Shuffle or permute a particular value.
     ///
     /// This method uses the technique described in Kensler's paper to perform an in-place shuffle
     /// with no memory overhead.
-    // We disable the `unreadable_literal` because these literals are arbitrary and don't really
-    // need to be readable anyways.
-    #[allow(clippy::unreadable_literal)]
     pub fn shuffle(&self, input: u32) -> PermutationResult<u32> {
         if input >= self.length.get() {
             return Err(PermutationError::ShuffleOutOfRange {
@@ -56,119 +486,1611 @@ impl HashedPermutation {
                 max_shuffle: self.length.get(),
             });
         }
-        let mut i = Wrapping(input);
-        let n = self.length.get();
-        let seed = Wrapping(self.seed);
-        let w = Wrapping(n.checked_next_power_of_two().map_or(u32::MAX, |x| x - 1));
-
-        while i.0 >= n {
-            i ^= seed;
-            i *= 0xe170893d;
-            i ^= seed >> 16;
-            i ^= (i & w) >> 4;
-            i ^= seed >> 8;
-            i *= 0x0929eb3f;
-            i ^= seed >> 23;
-            i ^= (i & w) >> 1;
-            i *= Wrapping(1) | seed >> 27;
-            i *= 0x6935fa69;
-            i ^= (i & w) >> 11;
-            i *= 0x74dcb303;
-            i ^= (i & w) >> 2;
-            i *= 0x9e501cc3;
-            i ^= (i & w) >> 2;
-            i *= 0xc860a3df;
-            i &= w;
-            i ^= i >> 5;
-        }
-        Ok((i + seed).0 % n)
+        Ok(self.permute(input))
     }
-}
 
-#[cfg(test)]
-mod test {
-    use super::*;
-    use std::collections::HashMap;
+    /// Shuffle a value and assign the result to one of `buckets` buckets.
+    ///
+    /// This returns `shuffle(input) * buckets / length`, which splits the outputs into contiguous
+    /// ranges of near-equal size, so the inputs are spread over the buckets as evenly as possible.
+    /// When `length` is divisible by `buckets`, every bucket receives exactly
+    /// `length / buckets` inputs.
+    ///
+    /// This returns an error if `buckets` is zero, or if `input` isn't within `0..length`.
+    pub fn bucket(&self, input: u32, buckets: u32) -> PermutationResult<u32> {
+        if buckets == 0 {
+            return Err(PermutationError::ZeroBuckets);
+        }
+        let output = self.shuffle(input)?;
+        Ok((u64::from(output) * u64::from(buckets) / u64::from(self.length.get())) as u32)
+    }
 
-    /// A convenient helper method that returns a pair of lengths and seeds (in that order).
+    /// Shuffle a value and map the result to the center of its stratum in `[0, 1)`.
     ///
-    /// This method defines the lengths and the seeds for the test cases, since these are reused
-    /// in the tests, and it's best practice to consolidate them in one place so code is not
-    /// repeated.
-    fn lengths_and_seeds() -> (Vec<NonZeroU32>, Vec<u32>) {
-        let lengths: Vec<NonZeroU32> = vec![100, 5, 13, 128, 249]
-            .iter()
-            .map(|&x| NonZeroU32::new(x).unwrap())
-            .collect();
-        let seeds = vec![100, 5, 13, 128, 249];
-        assert_eq!(lengths.len(), seeds.len());
-        (lengths, seeds)
+    /// The unit interval is split into `length` equal strata, and this returns the center of the
+    /// stratum that `shuffle(input)` selects, which is `(shuffle(input) + 0.5) / length`. Iterating
+    /// over `0..length` therefore yields one stratified sample per stratum, in a shuffled order.
+    pub fn shuffle_unit(&self, input: u32) -> PermutationResult<f64> {
+        let output = self.shuffle(input)?;
+        Ok((f64::from(output) + 0.5) / f64::from(self.length.get()))
     }
 
-    #[test]
-    // This method is a sanity check that tests to see if a shuffle has points that all stay within
-    // the domain that they are supposed to.
-    fn test_domain() {
-        let (lengths, seeds) = lengths_and_seeds();
+    /// Shuffle a value, also returning how many rounds of the hash it took.
+    ///
+    /// The result is `(shuffle(input), rounds)`. The hash works over the smallest power of two
+    /// range that covers `0..length`, so it is repeated until it lands within `0..length`, and
+    /// `rounds` is the number of times it ran. It is always at least one, and exactly one when
+    /// `length` is a power of two. This is meant for profiling how much extra work a particular
+    /// length costs.
+    pub fn shuffle_with_stats(&self, input: u32) -> PermutationResult<(u32, u32)> {
+        if input >= self.length.get() {
+            return Err(PermutationError::ShuffleOutOfRange {
+                shuffle: input,
+                max_shuffle: self.length.get(),
+            });
+        }
+        Ok(kensler_permute_with_rounds(
+            input,
+            self.length.get(),
+            self.seed,
+        ))
+    }
 
-        for (&length, seed) in lengths.iter().zip(seeds) {
-            let perm = HashedPermutation { seed, length };
+    /// Shuffle a value behind a reference.
+    ///
+    /// This is identical to [`HashedPermutation::shuffle`], but it takes `&u32`, so it fits into
+    /// iterator chains over references without dereferencing by hand.
+    pub fn shuffle_ref(&self, input: &u32) -> PermutationResult<u32> {
+        self.shuffle(*input)
+    }
 
-            for i in 0..perm.length.get() {
-                let res = perm.shuffle(i);
-                assert!(res.is_ok());
-                assert!(res.unwrap() < perm.length.get());
-            }
+    /// Shuffle a value, always running at least `rounds` rounds of the hash.
+    ///
+    /// [`HashedPermutation::shuffle`] repeats the hash until the result lands in `0..length`,
+    /// which is a data-dependent branch that can hurt throughput in hot loops over lengths that
+    /// aren't powers of two. This variant instead computes `rounds` rounds unconditionally and
+    /// keeps each one with a branch-free select only if the previous result was still out of
+    /// range. Only in the rare case where every one of those rounds lands out of range does it fall
+    /// back to looping.
+    ///
+    /// Simply running a fixed number of rounds over the whole power of two range and reducing the
+    /// result modulo `length` would not be a bijection, so this walks the exact same cycle as
+    /// `shuffle` and always returns the same value. Each round lands in range with a probability
+    /// of more than one half, so a handful of rounds avoids the fallback for almost every input.
+    pub fn shuffle_constant_rounds(&self, input: u32, rounds: u32) -> PermutationResult<u32> {
+        if input >= self.length.get() {
+            return Err(PermutationError::ShuffleOutOfRange {
+                shuffle: input,
+                max_shuffle: self.length.get(),
+            });
         }
+        Ok(kensler_permute_constant_rounds(
+            input,
+            self.length.get(),
+            self.seed,
+            rounds,
+        ))
     }
 
-    #[test]
-    // This method checks to see that a permutation does not have any collisions and that every
-    // number maps to another unique number. In other words, we are testing to see whether we have
-    // a bijective function.
-    fn test_bijection() {
-        let (lengths, seeds) = lengths_and_seeds();
-
-        for (length, seed) in lengths.iter().zip(seeds) {
-            let perm = HashedPermutation {
-                seed,
-                length: *length,
-            };
+    /// Iterate over every permuted index along with how many rounds of the hash it took.
+    ///
+    /// This yields `shuffle_with_stats(i)` for every `i` in `0..length`, in order, so the rounds
+    /// can be histogrammed to see how the extra work of a non power of two length is distributed.
+    pub fn iter_with_state(&self) -> impl Iterator<Item = (u32, u32)> + '_ {
+        let n = self.length.get();
+        (0..n).map(move |i| kensler_permute_with_rounds(i, n, self.seed))
+    }
 
-            // Check that each entry doesn't exist
-            // Check that every number is "hit" (as they'd have to be) for a perfect bijection
-            // Check that the number is within range
-            let mut map = HashMap::with_capacity(length.get() as usize);
+    /// Shuffle a fixed-size batch of values.
+    ///
+    /// The result holds the shuffled value of each input, so `result[k]` is `shuffle(inputs[k])`.
+    /// Because the size of the batch is known at compile time, the compiler
+    /// is free to unroll the loop. This returns an error for the first input that is not within
+    /// `0..length`.
+    pub fn shuffle_batch<const N: usize>(&self, inputs: [u32; N]) -> PermutationResult<[u32; N]> {
+        let mut outputs = [0; N];
 
-            for i in 0..perm.length.get() {
-                let res = perm.shuffle(i);
-                let res = res.unwrap();
-                assert!(map.insert(res, i).is_none());
-            }
-            let (mut keys_vec, mut vals_vec): (Vec<u32>, Vec<u32>) = map.iter().unzip();
-            keys_vec.sort();
-            vals_vec.sort();
-            let ground_truth: Vec<u32> = (0..length.get()).collect();
-            assert_eq!(ground_truth, keys_vec);
-            assert_eq!(ground_truth, vals_vec);
+        for (output, &input) in outputs.iter_mut().zip(inputs.iter()) {
+            *output = self.shuffle(input)?;
         }
+        Ok(outputs)
     }
 
-    #[test]
-    fn test_out_of_range() {
-        let lengths: Vec<NonZeroU32> = vec![1, 50, 256, 18]
+    /// Shuffle every value of a slice, stopping at the first one that is out of range.
+    ///
+    /// This is the dynamically sized counterpart of [`HashedPermutation::shuffle_batch`], for
+    /// validating an externally supplied list of inputs. `result[k]` is `shuffle(inputs[k])`. If
+    /// any input is not within `0..length`, this returns a
+    /// [`PermutationError::ShuffleOutOfRangeAt`] error with the position and value of the first
+    /// such input, without shuffling the rest.
+    pub fn shuffle_all_checked(&self, inputs: &[u32]) -> PermutationResult<Vec<u32>> {
+        inputs
             .iter()
-            .map(|&x| NonZeroU32::new(x).unwrap())
-            .collect();
-        let offsets = vec![0, 1, 5, 15, 100];
+            .enumerate()
+            .map(|(index, &input)| {
+                self.shuffle(input)
+                    .map_err(|_| PermutationError::ShuffleOutOfRangeAt {
+                        index,
+                        shuffle: input,
+                        max_shuffle: self.length.get(),
+                    })
+            })
+            .collect()
+    }
 
-        for length in lengths {
-            let perm = HashedPermutation { seed: 0, length };
+    /// Shuffle a signed value within the window `offset..offset + length`.
+    ///
+    /// The input is mapped into `0..length` by subtracting `offset`, shuffled, and then mapped
+    /// back by adding `offset`. This returns an error if `input - offset` is not within
+    /// `0..length`.
+    ///
+    /// The arithmetic wraps around, so a window that runs past `i32::MAX` continues from
+    /// `i32::MIN`. The result is still a bijection over the (wrapped) window.
+    ///
+    /// ```
+    /// # use hashed_permutation::HashedPermutation;
+    /// use std::num::NonZeroU32;
+    ///
+    /// // Permute the offsets in -5..5
+    /// let perm = HashedPermutation::new_with_seed(NonZeroU32::new(10).unwrap(), 1234);
+    /// let permuted = perm.shuffle_signed(-3, -5).unwrap();
+    /// assert!((-5..5).contains(&permuted));
+    /// ```
+    pub fn shuffle_signed(&self, input: i32, offset: i32) -> PermutationResult<i32> {
+        let shuffled = self.shuffle(input.wrapping_sub(offset) as u32)?;
+        Ok((shuffled as i32).wrapping_add(offset))
+    }
 
-            for offset in &offsets {
-                let result = perm.shuffle(length.get() + offset);
-                assert!(result.is_err());
-            }
+    /// Shuffle any `u32` by first reducing it modulo the length of the permutation.
+    ///
+    /// This is equivalent to `shuffle(input % length)`, so it never fails. Note that this is only
+    /// a bijection over `0..length`. Over all of `u32` it is not injective, since every input that
+    /// is congruent modulo `length` maps to the same output.
+    pub fn shuffle_wrapping(&self, input: u32) -> u32 {
+        self.permute(input % self.length.get())
+    }
+
+    /// Shuffle a coordinate of a 3D grid that is `width` voxels wide and `height` voxels tall.
+    ///
+    /// The coordinate is flattened to `z * width * height + y * width + x`, shuffled, and then
+    /// unflattened back into a coordinate, so a permutation over `width * height * depth` voxels
+    /// visits every voxel exactly once. This returns an error if `x` isn't within `0..width`, if
+    /// `y` isn't within `0..height`, or if the flattened index isn't within `0..length`.
+    pub fn shuffle_3d(
+        &self,
+        x: u32,
+        y: u32,
+        z: u32,
+        width: u32,
+        height: u32,
+    ) -> PermutationResult<(u32, u32, u32)> {
+        if x >= width {
+            return Err(PermutationError::ShuffleOutOfRange {
+                shuffle: x,
+                max_shuffle: width,
+            });
         }
+        if y >= height {
+            return Err(PermutationError::ShuffleOutOfRange {
+                shuffle: y,
+                max_shuffle: height,
+            });
+        }
+        let (width, height) = (u64::from(width), u64::from(height));
+        // An index that doesn't fit in a `u32` is out of range anyways, so saturate it and let
+        // `shuffle` report the error
+        let flat = u64::from(z)
+            .checked_mul(width * height)
+            .and_then(|flat| flat.checked_add(u64::from(y) * width + u64::from(x)))
+            .and_then(|flat| u32::try_from(flat).ok())
+            .unwrap_or(u32::MAX);
+        let output = u64::from(self.shuffle(flat)?);
+        Ok((
+            (output % width) as u32,
+            (output / width % height) as u32,
+            (output / (width * height)) as u32,
+        ))
+    }
+
+    /// Shuffle a value, widening the result to a `u64`.
+    ///
+    /// The result is the same as [`HashedPermutation::shuffle`], but widened so that callers can
+    /// multiply it by large factors without overflowing or casting it themselves.
+    pub fn shuffle_wide(&self, input: u32) -> PermutationResult<u64> {
+        self.shuffle(input).map(u64::from)
+    }
+
+    /// Shuffle a value and rotate the result by `offset`.
+    ///
+    /// This returns `(shuffle(input) + offset) % length`. Rotating the outputs keeps the mapping a
+    /// bijection, so each offset cheaply yields another permutation of the same family. Offsets of
+    /// `length` or more wrap around, so only `offset % length` matters.
+    pub fn shuffle_rotated(&self, input: u32, offset: u32) -> PermutationResult<u32> {
+        let output = self.shuffle(input)?;
+        let n = u64::from(self.length.get());
+        Ok(((u64::from(output) + u64::from(offset)) % n) as u32)
+    }
+
+    /// Shuffle any `u32` by first clamping it to `length - 1`.
+    ///
+    /// This never fails, which suits lenient APIs that would rather treat an out-of-range input
+    /// as the last valid index than report an error. Note that this is only a bijection over
+    /// `0..length`: every input at or past `length - 1` maps to the same output.
+    pub fn shuffle_clamped(&self, input: u32) -> u32 {
+        self.permute(input.min(self.length.get() - 1))
+    }
+
+    /// Hash an arbitrary `u32` with the same seeded mixing function that drives the permutation.
+    ///
+    /// This runs a single round of Kensler's hash over all 32 bits, without constraining the
+    /// output to `0..length`. The result does not depend on `length` at all and can be any `u32`,
+    /// so this is *not* the permutation over `0..length`: use [`HashedPermutation::shuffle`] for
+    /// that. It is meant for users who want a cheap, seeded, non-cryptographic integer hash.
+    pub fn hash(&self, input: u32) -> u32 {
+        kensler_round(Wrapping(input), Wrapping(u32::MAX), Wrapping(self.seed)).0
+    }
+
+    /// The number of bytes this permutation allocates on the heap.
+    ///
+    /// This is always zero: the permutation is computed on the fly, so the struct itself is all
+    /// the memory it needs. Materialized variants like [`crate::InversePermutation`] report the
+    /// size of their tables instead.
+    pub fn heap_bytes(&self) -> usize {
+        0
+    }
+
+    /// The number of bits spanned by the mask the permutation uses internally.
+    ///
+    /// The hash operates on the smallest power of two range that covers `0..length`, so this is
+    /// the number of bits needed to represent `length - 1`. For example, lengths of 16 and 17 use
+    /// 4 and 5 bits respectively.
+    pub fn mask_bits(&self) -> u32 {
+        32 - mask(self.length.get()).leading_zeros()
+    }
+
+    /// Shuffle a value without checking that it's within `0..length`.
+    ///
+    /// Callers are responsible for making sure that `input < length`, otherwise the result is
+    /// meaningless.
+    pub(crate) fn permute(&self, input: u32) -> u32 {
+        kensler_permute(input, self.length.get(), self.seed)
+    }
+}
+
+/// Materialize the whole permutation, so `vec[i]` is `shuffle(i)`.
+impl From<&HashedPermutation> for Vec<u32> {
+    fn from(perm: &HashedPermutation) -> Self {
+        perm.iter().collect()
+    }
+}
+
+/// A hashed permutation over a domain of at most `u16::MAX` elements.
+///
+/// This is the 16 bit counterpart of [`HashedPermutation`], for small lookup tables where the
+/// narrower types save space and make the intent clearer. The rounds are adapted to 16 bit
+/// arithmetic, so it yields a different permutation than a [`HashedPermutation`] with the same
+/// seed and length.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HashedPermutation16 {
+    /// The random seed that dictates which permutation you want to use
+    pub seed: u16,
+
+    /// The upper bound on the range of numbers to shuffle (from `0..length`)
+    pub length: NonZeroU16,
+}
+
+impl HashedPermutation16 {
+    /// Create a new instance of the hashed permutation given a length and seed
+    pub fn new_with_seed(length: NonZeroU16, seed: u16) -> Self {
+        HashedPermutation16 { length, seed }
+    }
+
+    /// Shuffle or permute a particular value.
+    ///
+    /// This returns an error if `input` is not within `0..length`.
+    pub fn shuffle(&self, input: u16) -> PermutationResult<u16> {
+        if input >= self.length.get() {
+            return Err(PermutationError::ShuffleOutOfRange {
+                shuffle: u32::from(input),
+                max_shuffle: u32::from(self.length.get()),
+            });
+        }
+        Ok(kensler_permute16(input, self.length.get(), self.seed))
+    }
+}
+
+/// Check whether `values` holds every index in `0..length` exactly once.
+pub(crate) fn is_permutation(values: impl IntoIterator<Item = u32>, length: u32) -> bool {
+    let mut seen = vec![false; length as usize];
+    let mut count = 0;
+
+    for value in values {
+        match seen.get_mut(value as usize) {
+            Some(seen) if !*seen => *seen = true,
+            _ => return false,
+        }
+        count += 1;
+    }
+    count == length
+}
+
+/// The core of Kensler's permutation, shuffling `input` within `0..n`.
+///
+/// `n` must be greater than zero and `input` must be less than `n`, otherwise the result is
+/// meaningless.
+pub(crate) fn kensler_permute(input: u32, n: u32, seed: u32) -> u32 {
+    kensler_permute_with_rounds(input, n, seed).0
+}
+
+/// Kensler's permutation, also returning the number of hash rounds it took to land in `0..n`.
+///
+/// This is always at least one. When `n` is a power of two every round lands in range, so it's
+/// exactly one.
+// This sits in the hot loop of every shuffle, so it's important that it has no panicking paths,
+// even in debug builds and for extreme inputs:
+// - The hashing is done with `Wrapping`, so the multiplications wrap instead of overflowing.
+// - Every shift amount is a constant below 32.
+// - `mask` uses `checked_next_power_of_two`, so lengths above 2^31 saturate to a full mask
+//   instead of overflowing.
+// - The seed is added in `u64`, which can't overflow, and `n` is nonzero, so the final `%` can't
+//   divide by zero.
+// - The loop terminates, since each round is a bijection over `0..=w`, so walking the cycle of an
+//   index below `n` must eventually land below `n` again.
+// - Every round but the last lands on a distinct index in `n..=w`, so the round counter is at
+//   most `w - n + 2`, which is far below `u32::MAX`.
+#[inline]
+pub(crate) fn kensler_permute_with_rounds(input: u32, n: u32, seed: u32) -> (u32, u32) {
+    let mut i = Wrapping(input);
+    let seed = Wrapping(seed);
+    let w = Wrapping(mask(n));
+    let mut rounds = 0;
+
+    // Like Kensler's original, this hashes at least once, and keeps hashing until the result is
+    // back in range.
+    loop {
+        i = kensler_round(i, w, seed);
+        rounds += 1;
+
+        if i.0 < n {
+            break;
+        }
+    }
+    // Widen before adding the seed, otherwise the sum can wrap around `u32::MAX` for large seeds
+    // and the offset is no longer a rotation, which breaks the bijection.
+    let result = ((u64::from(i.0) + u64::from(seed.0)) % u64::from(n)) as u32;
+    (result, rounds)
+}
+
+/// A variant of [`kensler_permute`] that always computes at least `rounds` hash rounds, selecting
+/// between them without branching, so for almost every input the running time doesn't depend on
+/// the input.
+///
+/// `n` must be greater than zero and `input` must be less than `n`, otherwise the result is
+/// meaningless. The result is always the same as `kensler_permute(input, n, seed)`.
+fn kensler_permute_constant_rounds(input: u32, n: u32, seed: u32, rounds: u32) -> u32 {
+    let seed = Wrapping(seed);
+    let w = Wrapping(mask(n));
+    let mut i = kensler_round(Wrapping(input), w, seed);
+
+    for _ in 1..rounds {
+        let next = kensler_round(i, w, seed);
+        // Once the walk is back in range it has to stay put, which the compiler can lower to a
+        // conditional move
+        i = if i.0 >= n { next } else { i };
+    }
+    // Fall back to the regular loop in the rare case that every round landed out of range
+    while i.0 >= n {
+        i = kensler_round(i, w, seed);
+    }
+    ((u64::from(i.0) + u64::from(seed.0)) % u64::from(n)) as u32
+}
+
+/// The mask that covers every index in `0..n`, i.e. one less than the smallest power of two that
+/// is at least `n`.
+fn mask(n: u32) -> u32 {
+    n.checked_next_power_of_two().map_or(u32::MAX, |x| x - 1)
+}
+
+/// A single round of Kensler's hash, which is a bijection over the bits selected by the mask `w`.
+// We disable the `unreadable_literal` because these literals are arbitrary and don't really
+// need to be readable anyways.
+#[allow(clippy::unreadable_literal)]
+fn kensler_round(mut i: Wrapping<u32>, w: Wrapping<u32>, seed: Wrapping<u32>) -> Wrapping<u32> {
+    i ^= seed;
+    i *= 0xe170893d;
+    i ^= seed >> 16;
+    i ^= (i & w) >> 4;
+    i ^= seed >> 8;
+    i *= 0x0929eb3f;
+    i ^= seed >> 23;
+    i ^= (i & w) >> 1;
+    i *= Wrapping(1) | seed >> 27;
+    i *= 0x6935fa69;
+    i ^= (i & w) >> 11;
+    i *= 0x74dcb303;
+    i ^= (i & w) >> 2;
+    i *= 0x9e501cc3;
+    i ^= (i & w) >> 2;
+    i *= 0xc860a3df;
+    i &= w;
+    i ^= i >> 5;
+    i
+}
+
+/// The 16 bit version of [`kensler_permute`], shuffling `input` within `0..n`.
+///
+/// `n` must be greater than zero and `input` must be less than `n`, otherwise the result is
+/// meaningless.
+fn kensler_permute16(input: u16, n: u16, seed: u16) -> u16 {
+    let mut i = Wrapping(input);
+    let seed = Wrapping(seed);
+    let w = Wrapping(mask16(n));
+
+    loop {
+        i = kensler_round16(i, w, seed);
+
+        if i.0 < n {
+            break;
+        }
+    }
+    ((u32::from(i.0) + u32::from(seed.0)) % u32::from(n)) as u16
+}
+
+/// The 16 bit version of [`mask`].
+fn mask16(n: u16) -> u16 {
+    n.checked_next_power_of_two().map_or(u16::MAX, |x| x - 1)
+}
+
+/// The 16 bit version of [`kensler_round`].
+///
+/// The multipliers are the low halves of the 32 bit ones, which are all still odd, so every
+/// multiplication remains a bijection over the masked bits. The shifts are scaled down to fit in
+/// 16 bits.
+fn kensler_round16(mut i: Wrapping<u16>, w: Wrapping<u16>, seed: Wrapping<u16>) -> Wrapping<u16> {
+    i ^= seed;
+    i *= 0x893d;
+    i ^= seed >> 8;
+    i ^= (i & w) >> 4;
+    i ^= seed >> 4;
+    i *= 0xeb3f;
+    i ^= seed >> 11;
+    i ^= (i & w) >> 1;
+    i *= Wrapping(1) | seed >> 13;
+    i *= 0xfa69;
+    i ^= (i & w) >> 6;
+    i *= 0xb303;
+    i ^= (i & w) >> 2;
+    i *= 0x1cc3;
+    i ^= (i & w) >> 2;
+    i *= 0xa3df;
+    i &= w;
+    i ^= i >> 5;
+    i
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::lengths_and_seeds;
+    use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+
+    #[test]
+    // This method is a sanity check that tests to see if a shuffle has points that all stay within
+    // the domain that they are supposed to.
+    fn test_domain() {
+        let (lengths, seeds) = lengths_and_seeds();
+
+        for (&length, seed) in lengths.iter().zip(seeds) {
+            let perm = HashedPermutation { seed, length };
+
+            for i in 0..perm.length.get() {
+                let res = perm.shuffle(i);
+                assert!(res.is_ok());
+                assert!(res.unwrap() < perm.length.get());
+            }
+        }
+    }
+
+    #[test]
+    // This method checks to see that a permutation does not have any collisions and that every
+    // number maps to another unique number. In other words, we are testing to see whether we have
+    // a bijective function.
+    fn test_bijection() {
+        let (lengths, seeds) = lengths_and_seeds();
+
+        for (length, seed) in lengths.iter().zip(seeds) {
+            let perm = HashedPermutation {
+                seed,
+                length: *length,
+            };
+
+            // Check that each entry doesn't exist
+            // Check that every number is "hit" (as they'd have to be) for a perfect bijection
+            // Check that the number is within range
+            let mut map = HashMap::with_capacity(length.get() as usize);
+
+            for i in 0..perm.length.get() {
+                let res = perm.shuffle(i);
+                let res = res.unwrap();
+                assert!(map.insert(res, i).is_none());
+            }
+            let (mut keys_vec, mut vals_vec): (Vec<u32>, Vec<u32>) = map.iter().unzip();
+            keys_vec.sort();
+            vals_vec.sort();
+            let ground_truth: Vec<u32> = (0..length.get()).collect();
+            assert_eq!(ground_truth, keys_vec);
+            assert_eq!(ground_truth, vals_vec);
+        }
+    }
+
+    #[test]
+    // Seeds close to `u32::MAX` used to overflow when the seed was added to the result, so we
+    // make sure those are still bijections.
+    fn test_bijection_large_seeds() {
+        let (lengths, _) = lengths_and_seeds();
+
+        for length in lengths {
+            for seed in [u32::MAX, u32::MAX - 1, u32::MAX - length.get()] {
+                let perm = HashedPermutation::new_with_seed(length, seed);
+                let mut seen = vec![false; length.get() as usize];
+
+                for i in 0..length.get() {
+                    let res = perm.shuffle(i).unwrap() as usize;
+                    assert!(!seen[res]);
+                    seen[res] = true;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_new_checked() {
+        let (lengths, seeds) = lengths_and_seeds();
+
+        for (&length, seed) in lengths.iter().zip(seeds) {
+            // The check should only run for small lengths in debug builds
+            assert_eq!(
+                HashedPermutation::checks_bijection(length),
+                cfg!(debug_assertions)
+            );
+            let perm = HashedPermutation::new_checked(length, seed).unwrap();
+            let expected = HashedPermutation::new_with_seed(length, seed);
+            assert_eq!(perm.length, length);
+            assert_eq!(perm.seed, seed);
+            assert!(perm.unchecked_iter().eq(expected.unchecked_iter()));
+        }
+        let large = NonZeroU32::new(HashedPermutation::BIJECTION_CHECK_MAX_LENGTH + 1).unwrap();
+        assert!(!HashedPermutation::checks_bijection(large));
+        assert!(HashedPermutation::new_checked(large, 0).is_ok());
+    }
+
+    #[test]
+    // Every seed yields a bijection, so the failing side of the check that `new_checked` runs is
+    // tested on tables that aren't one: a repeated index, an index out of range, and too few
+    // indices.
+    fn test_check_bijection() {
+        let length = NonZeroU32::new(4).unwrap();
+        assert!(HashedPermutation::check_bijection(vec![2, 0, 3, 1], length).is_ok());
+
+        for values in [vec![2, 0, 2, 1], vec![2, 0, 4, 1], vec![2, 0, 1]] {
+            assert!(matches!(
+                HashedPermutation::check_bijection(values, length),
+                Err(PermutationError::NotAPermutation { length: 4 })
+            ));
+        }
+    }
+
+    #[test]
+    fn test_take_iter() {
+        let (lengths, seeds) = lengths_and_seeds();
+
+        for (&length, seed) in lengths.iter().zip(seeds) {
+            let perm = HashedPermutation::new_with_seed(length, seed);
+
+            for &n in &[0, 1, length.get() / 2, length.get(), length.get() + 10] {
+                let items: Vec<u32> = perm.take_iter(n).collect();
+                assert_eq!(items.len() as u32, n.min(length.get()));
+
+                let set: HashSet<u32> = items.iter().copied().collect();
+                assert_eq!(set.len(), items.len());
+
+                for (i, &item) in items.iter().enumerate() {
+                    assert_eq!(item, perm.shuffle(i as u32).unwrap());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_from_inclusive() {
+        for &end in &[0, 1, 12, 99, 248] {
+            let perm = HashedPermutation::from_inclusive(end, 100).unwrap();
+            assert_eq!(perm.length.get(), end + 1);
+            assert!(perm.shuffle(end).is_ok());
+            assert!(perm.shuffle(end + 1).is_err());
+        }
+        let perm = HashedPermutation::from_inclusive(u32::MAX - 1, 100).unwrap();
+        assert_eq!(perm.length.get(), u32::MAX);
+        assert!(matches!(
+            HashedPermutation::from_inclusive(u32::MAX, 100),
+            Err(PermutationError::LengthOverflow { end: u32::MAX })
+        ));
+    }
+
+    #[test]
+    fn test_interleaved_iter() {
+        let (lengths, seeds) = lengths_and_seeds();
+
+        for (&length, seed) in lengths.iter().zip(seeds) {
+            let perm = HashedPermutation::new_with_seed(length, seed);
+
+            // 7 is coprime to all of the lengths, 4 isn't coprime to some, and the last stride is
+            // larger than the length
+            for &stride in &[1, 4, 7, length.get() + 1] {
+                let mut items: Vec<u32> = perm.interleaved_iter(stride).collect();
+                items.sort_unstable();
+                let expected: Vec<u32> = (0..length.get()).collect();
+                assert_eq!(expected, items);
+            }
+        }
+    }
+
+    #[test]
+    fn test_as_fn() {
+        let (lengths, seeds) = lengths_and_seeds();
+
+        for (&length, seed) in lengths.iter().zip(seeds) {
+            let perm = HashedPermutation::new_with_seed(length, seed);
+            let mut items: Vec<u32> = (0..length.get()).map(perm.as_fn()).collect();
+
+            for (i, &item) in items.iter().enumerate() {
+                assert_eq!(item, perm.shuffle(i as u32).unwrap());
+            }
+            items.sort_unstable();
+            let expected: Vec<u32> = (0..length.get()).collect();
+            assert_eq!(expected, items);
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_as_fn_out_of_range() {
+        let perm = HashedPermutation::new_with_seed(NonZeroU32::new(10).unwrap(), 0);
+        let f = perm.as_fn();
+        f(10);
+    }
+
+    #[test]
+    fn test_find_seed() {
+        let length = NonZeroU32::new(100).unwrap();
+        let perm = HashedPermutation::find_seed(length, &[(5, 42)], 10_000).unwrap();
+        assert_eq!(perm.length, length);
+        assert_eq!(perm.shuffle(5).unwrap(), 42);
+
+        // Constraints that can never be satisfied should exhaust the search
+        assert!(HashedPermutation::find_seed(length, &[(5, 100)], 100).is_none());
+        assert!(HashedPermutation::find_seed(length, &[(0, 1), (1, 1)], 100).is_none());
+        assert!(HashedPermutation::find_seed(length, &[(5, 42)], 0).is_none());
+    }
+
+    #[test]
+    fn test_collect_into() {
+        let (lengths, seeds) = lengths_and_seeds();
+
+        for (&length, seed) in lengths.iter().zip(seeds) {
+            let perm = HashedPermutation::new_with_seed(length, seed);
+
+            let set: BTreeSet<u32> = perm.collect_into();
+            assert_eq!(set.len(), length.get() as usize);
+            assert!(set.iter().copied().eq(0..length.get()));
+
+            let deque: VecDeque<u32> = perm.collect_into();
+            assert!(deque.into_iter().eq(perm.iter()));
+        }
+    }
+
+    #[test]
+    fn test_shuffle_wrapping() {
+        let (lengths, seeds) = lengths_and_seeds();
+
+        for (&length, seed) in lengths.iter().zip(seeds) {
+            let perm = HashedPermutation::new_with_seed(length, seed);
+
+            for &x in &[0, 1, length.get() - 1, length.get(), 1000, u32::MAX] {
+                assert_eq!(
+                    perm.shuffle_wrapping(x),
+                    perm.shuffle(x % length.get()).unwrap()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_pairs_iter() {
+        let (lengths, seeds) = lengths_and_seeds();
+
+        for (&length, seed) in lengths.iter().zip(seeds) {
+            let perm = HashedPermutation::new_with_seed(length, seed);
+            let pairs: Vec<(u32, u32)> = perm.pairs_iter().collect();
+            assert_eq!(pairs.len() as u32, length.get() / 2);
+
+            let mut items: Vec<u32> = pairs.iter().flat_map(|&(a, b)| vec![a, b]).collect();
+            assert!(items
+                .iter()
+                .copied()
+                .eq(perm.take_iter(length.get() / 2 * 2)));
+
+            // The leftover element for odd lengths is the last one in the permutation
+            if length.get() % 2 == 1 {
+                items.push(perm.shuffle(length.get() - 1).unwrap());
+            }
+            items.sort_unstable();
+            let expected: Vec<u32> = (0..length.get()).collect();
+            assert_eq!(expected, items);
+        }
+    }
+
+    #[test]
+    fn test_shuffle_batch() {
+        let (lengths, seeds) = lengths_and_seeds();
+
+        for (&length, seed) in lengths.iter().zip(seeds) {
+            let perm = HashedPermutation::new_with_seed(length, seed);
+            let inputs = [0, 3, 1, length.get() - 1];
+            let outputs = perm.shuffle_batch(inputs).unwrap();
+
+            for (&input, &output) in inputs.iter().zip(outputs.iter()) {
+                assert_eq!(output, perm.shuffle(input).unwrap());
+            }
+
+            let result = perm.shuffle_batch([0, 1, length.get(), length.get() + 1]);
+            assert!(matches!(
+                result,
+                Err(PermutationError::ShuffleOutOfRange { shuffle, .. }) if shuffle == length.get()
+            ));
+        }
+    }
+
+    #[test]
+    fn test_shuffle_all_checked() {
+        let (lengths, seeds) = lengths_and_seeds();
+
+        for (&length, seed) in lengths.iter().zip(seeds) {
+            let perm = HashedPermutation::new_with_seed(length, seed);
+            let n = length.get();
+            let inputs = [0, 3, 1, n - 1, n / 2];
+            let outputs = perm.shuffle_all_checked(&inputs).unwrap();
+            assert_eq!(outputs.len(), inputs.len());
+
+            for (&input, &output) in inputs.iter().zip(outputs.iter()) {
+                assert_eq!(output, perm.shuffle(input).unwrap());
+            }
+            assert!(perm.shuffle_all_checked(&[]).unwrap().is_empty());
+
+            let result = perm.shuffle_all_checked(&[0, 1, n + 7, n - 1, n]);
+            assert!(matches!(
+                result,
+                Err(PermutationError::ShuffleOutOfRangeAt { index, shuffle, max_shuffle })
+                    if index == 2 && shuffle == n + 7 && max_shuffle == n
+            ));
+        }
+    }
+
+    #[test]
+    fn test_seeds_iter() {
+        let (lengths, seeds) = lengths_and_seeds();
+
+        for (&length, seed) in lengths.iter().zip(seeds) {
+            let perms: Vec<HashedPermutation> = HashedPermutation::seeds_iter(seed, length)
+                .take(10)
+                .collect();
+            assert_eq!(perms.len(), 10);
+            assert!(perms.iter().all(|perm| perm.length == length));
+
+            // Consecutive epochs should shuffle the indices in a different order
+            let orders: Vec<Vec<u32>> = perms.iter().map(|perm| perm.iter().collect()).collect();
+
+            for pair in orders.windows(2) {
+                assert_eq!(pair[0].len(), length.get() as usize);
+                assert_ne!(pair[0], pair[1]);
+            }
+        }
+    }
+
+    #[test]
+    // Flipping a single bit of the input should flip roughly half of the bits of the output, on
+    // average.
+    fn test_hash_avalanche() {
+        let perm = HashedPermutation::new_with_seed(NonZeroU32::new(100).unwrap(), 1234);
+        let mut flipped_bits = 0_u64;
+        let mut trials = 0_u64;
+
+        for input in (0..1_000_000).step_by(997) {
+            let hash = perm.hash(input);
+
+            for bit in 0..32 {
+                flipped_bits += u64::from((hash ^ perm.hash(input ^ (1 << bit))).count_ones());
+                trials += 1;
+            }
+        }
+        let mean = flipped_bits as f64 / trials as f64;
+        assert!((12.0..20.0).contains(&mean), "mean flipped bits: {}", mean);
+    }
+
+    #[test]
+    fn test_mask_bits() {
+        let cases = [
+            (1, 0),
+            (2, 1),
+            (16, 4),
+            (17, 5),
+            (256, 8),
+            (257, 9),
+            (1 << 31, 31),
+            ((1 << 31) + 1, 32),
+            (u32::MAX, 32),
+        ];
+
+        for &(length, bits) in &cases {
+            let perm = HashedPermutation::new_with_seed(NonZeroU32::new(length).unwrap(), 0);
+            assert_eq!(perm.mask_bits(), bits, "length: {}", length);
+        }
+    }
+
+    #[test]
+    fn test_split_at() {
+        let (lengths, seeds) = lengths_and_seeds();
+
+        for (&length, seed) in lengths.iter().zip(seeds) {
+            let perm = HashedPermutation::new_with_seed(length, seed);
+
+            for &mid in &[0, 1, length.get() / 2, length.get()] {
+                let (first, second) = perm.split_at(mid).unwrap();
+                assert_eq!(first.len() as u32, mid);
+                assert_eq!(second.len() as u32, length.get() - mid);
+
+                let first: Vec<u32> = first.collect();
+                let second: Vec<u32> = second.collect();
+                assert!(first.iter().all(|x| !second.contains(x)));
+                assert!(first.into_iter().chain(second).eq(perm.iter()));
+            }
+            assert!(perm.split_at(length.get() + 1).is_err());
+        }
+    }
+
+    #[test]
+    fn test_heap_bytes() {
+        let (lengths, seeds) = lengths_and_seeds();
+
+        for (&length, seed) in lengths.iter().zip(seeds) {
+            let perm = HashedPermutation::new_with_seed(length, seed);
+            assert_eq!(perm.heap_bytes(), 0);
+        }
+    }
+
+    #[test]
+    fn test_new_bounded() {
+        let length = NonZeroU32::new(100).unwrap();
+        let perm = HashedPermutation::new_bounded(length, 5, 100).unwrap();
+        assert_eq!(perm.length, length);
+        assert_eq!(perm.seed, 5);
+        assert!(HashedPermutation::new_bounded(length, 5, u32::MAX).is_ok());
+
+        assert!(matches!(
+            HashedPermutation::new_bounded(length, 5, 99),
+            Err(PermutationError::LengthTooLarge {
+                length: 100,
+                max: 99
+            })
+        ));
+        assert!(HashedPermutation::new_bounded(length, 5, 0).is_err());
+    }
+
+    #[test]
+    fn test_shuffle_signed() {
+        let (lengths, seeds) = lengths_and_seeds();
+
+        for (&length, seed) in lengths.iter().zip(seeds) {
+            let perm = HashedPermutation::new_with_seed(length, seed);
+            let k = (length.get() / 2) as i32;
+            let range = -k..(length.get() as i32 - k);
+            let mut results: Vec<i32> = range
+                .clone()
+                .map(|x| perm.shuffle_signed(x, -k).unwrap())
+                .collect();
+            results.sort_unstable();
+            assert!(results.into_iter().eq(range.clone()));
+
+            assert!(perm.shuffle_signed(range.start - 1, -k).is_err());
+            assert!(perm.shuffle_signed(range.end, -k).is_err());
+        }
+    }
+
+    #[test]
+    // A window that runs past `i32::MAX` wraps around to `i32::MIN`, but is still a bijection.
+    fn test_shuffle_signed_wrapping() {
+        let perm = HashedPermutation::new_with_seed(NonZeroU32::new(10).unwrap(), 5);
+        let offset = i32::MAX - 4;
+        let window: Vec<i32> = (0..10).map(|x| offset.wrapping_add(x)).collect();
+        let mut results: Vec<i32> = window
+            .iter()
+            .map(|&x| perm.shuffle_signed(x, offset).unwrap())
+            .collect();
+        results.sort_unstable();
+        let mut expected = window;
+        expected.sort_unstable();
+        assert_eq!(expected, results);
+    }
+
+    #[test]
+    fn test_with_length() {
+        let (lengths, seeds) = lengths_and_seeds();
+
+        for (&length, seed) in lengths.iter().zip(seeds) {
+            let perm = HashedPermutation::new_with_seed(length, seed);
+            let new_length = NonZeroU32::new(length.get() * 2 + 1).unwrap();
+            let resized = perm.with_length(new_length);
+            assert_eq!(resized.seed, seed);
+            assert_eq!(resized.length, new_length);
+            assert_eq!(perm.length, length);
+        }
+    }
+
+    #[test]
+    fn test_into_vec() {
+        let (lengths, seeds) = lengths_and_seeds();
+
+        for (&length, seed) in lengths.iter().zip(seeds) {
+            let perm = HashedPermutation::new_with_seed(length, seed);
+            let mut v: Vec<u32> = (&perm).into();
+            assert!(v.iter().copied().eq(perm.iter()));
+
+            v.sort_unstable();
+            let expected: Vec<u32> = (0..length.get()).collect();
+            assert_eq!(expected, v);
+        }
+    }
+
+    #[test]
+    // None of the arithmetic in the shuffle should panic, even at the edges of the input space.
+    fn test_extreme_inputs() {
+        let lengths = [1, 2, 3, 1 << 31, (1 << 31) + 1, u32::MAX - 1, u32::MAX];
+        let seeds = [0, 1, 1 << 31, u32::MAX - 1, u32::MAX];
+
+        for &length in &lengths {
+            let perm = HashedPermutation::new_with_seed(NonZeroU32::new(length).unwrap(), 0);
+
+            for &seed in &seeds {
+                let perm = HashedPermutation {
+                    seed,
+                    ..perm.clone()
+                };
+
+                for &input in &[0, 1, length / 2, length - 1] {
+                    if input < length {
+                        assert!(perm.shuffle(input).unwrap() < length);
+                    }
+                }
+                assert!(perm.shuffle(u32::MAX).is_err());
+                assert!(perm.shuffle_wrapping(u32::MAX) < length);
+                perm.hash(u32::MAX);
+                perm.mask_bits();
+            }
+        }
+    }
+
+    #[test]
+    // Every input has to be hashed at least once. If in-range inputs skipped the hash, the shuffle
+    // would just be a rotation, with the same `(shuffle(i) - i) mod n` offset for every `i`.
+    fn test_not_a_rotation() {
+        let (lengths, seeds) = lengths_and_seeds();
+
+        for (&length, seed) in lengths.iter().zip(seeds) {
+            let perm = HashedPermutation::new_with_seed(length, seed);
+            let n = length.get();
+            let offsets: Vec<u32> = (0..n)
+                .map(|i| (perm.shuffle(i).unwrap() + n - i) % n)
+                .collect();
+            assert!(offsets.iter().any(|&offset| offset != offsets[0]));
+        }
+    }
+
+    #[test]
+    fn test_out_of_range() {
+        let lengths: Vec<NonZeroU32> = vec![1, 50, 256, 18]
+            .iter()
+            .map(|&x| NonZeroU32::new(x).unwrap())
+            .collect();
+        let offsets = vec![0, 1, 5, 15, 100];
+
+        for length in lengths {
+            let perm = HashedPermutation { seed: 0, length };
+
+            for offset in &offsets {
+                let result = perm.shuffle(length.get() + offset);
+                assert!(result.is_err());
+            }
+        }
+    }
+
+    #[test]
+    // The 16 bit permutation should stay within its domain, hit every value exactly once, and
+    // reject inputs outside of the domain.
+    fn test_hashed_permutation16() {
+        let lengths = [100, 5, 13, 128, 249, 1, 256, u16::MAX];
+        let seeds = [100, 5, 13, 128, 249, 0, u16::MAX, 0x5eed];
+
+        for (&length, &seed) in lengths.iter().zip(seeds.iter()) {
+            let perm = HashedPermutation16::new_with_seed(NonZeroU16::new(length).unwrap(), seed);
+            let mut set = HashSet::with_capacity(length as usize);
+
+            for i in 0..length {
+                let res = perm.shuffle(i).unwrap();
+                assert!(res < length);
+                assert!(set.insert(res));
+            }
+            assert_eq!(set.len(), length as usize);
+            assert!(perm.shuffle(length).is_err());
+            assert!(perm.shuffle(u16::MAX).is_err());
+        }
+    }
+
+    #[test]
+    // Like the 32 bit permutation, every input is hashed at least once, so the shuffle shouldn't
+    // be a rotation with the same `(shuffle(i) - i) mod n` offset for every `i`.
+    fn test_hashed_permutation16_not_a_rotation() {
+        let (lengths, seeds) = lengths_and_seeds();
+
+        for (&length, seed) in lengths.iter().zip(seeds) {
+            let n = length.get() as u16;
+            let perm = HashedPermutation16::new_with_seed(NonZeroU16::new(n).unwrap(), seed as u16);
+            let offsets: Vec<u16> = (0..n)
+                .map(|i| (perm.shuffle(i).unwrap() + n - i) % n)
+                .collect();
+            assert!(offsets.iter().any(|&offset| offset != offsets[0]));
+        }
+    }
+
+    #[test]
+    // Deriving a child should be deterministic, keep the length, and usually change the ordering.
+    fn test_derive_child() {
+        let (lengths, seeds) = lengths_and_seeds();
+        let mut changed = 0;
+
+        for (&length, seed) in lengths.iter().zip(seeds) {
+            let perm = HashedPermutation::new_with_seed(length, seed);
+            let child = perm.derive_child();
+            assert_eq!(child.length, length);
+            assert_eq!(child.seed, perm.derive_child().seed);
+
+            if !perm.iter().eq(child.iter()) {
+                changed += 1;
+            }
+        }
+        assert!(changed >= lengths.len() - 1);
+    }
+
+    #[test]
+    fn test_unchecked_iter() {
+        let (lengths, seeds) = lengths_and_seeds();
+
+        for (&length, seed) in lengths.iter().zip(seeds) {
+            let perm = HashedPermutation::new_with_seed(length, seed);
+            assert!(perm.unchecked_iter().eq(perm.iter()));
+        }
+    }
+
+    #[test]
+    // Sharding the outputs by value should emit every value exactly once, in permutation order.
+    fn test_outputs_in_range() {
+        let (lengths, seeds) = lengths_and_seeds();
+
+        for (&length, seed) in lengths.iter().zip(seeds) {
+            let perm = HashedPermutation::new_with_seed(length, seed);
+            let n = length.get();
+            let bounds = [0, n / 3, n / 3, n / 2, n];
+            let mut seen = HashSet::new();
+
+            for shard in bounds.windows(2) {
+                let (lo, hi) = (shard[0], shard[1]);
+                let outputs: Vec<u32> = perm.outputs_in_range(lo, hi).unwrap().collect();
+                let expected: Vec<u32> = perm.iter().filter(|&x| lo <= x && x < hi).collect();
+                assert_eq!(outputs, expected);
+
+                for x in outputs {
+                    assert!(seen.insert(x));
+                }
+            }
+            assert_eq!(seen.len(), n as usize);
+            assert!(perm.outputs_in_range(0, n + 1).is_err());
+            assert!(perm.outputs_in_range(2, 1).is_err());
+        }
+    }
+
+    #[test]
+    // The result should match `shuffle`, and powers of two should only ever take a single round.
+    fn test_shuffle_with_stats() {
+        let (lengths, seeds) = lengths_and_seeds();
+
+        for (&length, seed) in lengths.iter().zip(seeds) {
+            let perm = HashedPermutation::new_with_seed(length, seed);
+            let is_power_of_two = length.get().is_power_of_two();
+
+            for i in 0..length.get() {
+                let (res, rounds) = perm.shuffle_with_stats(i).unwrap();
+                assert_eq!(res, perm.shuffle(i).unwrap());
+                assert!(rounds >= 1);
+
+                if is_power_of_two {
+                    assert_eq!(rounds, 1);
+                }
+            }
+            assert!(perm.shuffle_with_stats(length.get()).is_err());
+        }
+    }
+
+    #[test]
+    #[cfg(all(feature = "getrandom", not(feature = "use-rand")))]
+    fn test_new_getrandom() {
+        let length = NonZeroU32::new(100).unwrap();
+        let perm = HashedPermutation::new(length);
+        let set: HashSet<u32> = perm.iter().collect();
+        assert_eq!(perm.length, length);
+        assert_eq!(set.len(), 100);
+        assert!(set.iter().all(|&x| x < 100));
+    }
+
+    #[test]
+    // Every index should land in exactly one ordered pair, and odd lengths should be rejected.
+    fn test_shuffle_pair_symmetric() {
+        let (lengths, seeds) = lengths_and_seeds();
+
+        for (&length, seed) in lengths.iter().zip(seeds) {
+            let perm = HashedPermutation::new_with_seed(length, seed);
+
+            if length.get() & 1 != 0 {
+                assert!(perm.shuffle_pair_symmetric().is_err());
+                continue;
+            }
+            let pairs = perm.shuffle_pair_symmetric().unwrap();
+            let mut seen = HashSet::new();
+            assert_eq!(pairs.len() as u32, length.get() / 2);
+
+            for (a, b) in pairs {
+                assert!(a < b);
+                assert!(seen.insert(a));
+                assert!(seen.insert(b));
+            }
+            assert_eq!(seen.len() as u32, length.get());
+        }
+    }
+
+    #[test]
+    // Every point should be distinct and lie in `[0, 1)`.
+    fn test_shuffle_unit() {
+        let (lengths, seeds) = lengths_and_seeds();
+
+        for (&length, seed) in lengths.iter().zip(seeds) {
+            let perm = HashedPermutation::new_with_seed(length, seed);
+            let mut points: Vec<f64> = (0..length.get())
+                .map(|i| perm.shuffle_unit(i).unwrap())
+                .collect();
+            assert!(points.iter().all(|x| (0.0..1.0).contains(x)));
+            points.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            assert!(points.windows(2).all(|w| w[0] < w[1]));
+            assert!(perm.shuffle_unit(length.get()).is_err());
+        }
+    }
+
+    #[test]
+    // The result should be sorted, distinct, and hold exactly the first `k` shuffles.
+    fn test_first_k_sorted() {
+        let (lengths, seeds) = lengths_and_seeds();
+
+        for (&length, seed) in lengths.iter().zip(seeds) {
+            let perm = HashedPermutation::new_with_seed(length, seed);
+
+            for &k in &[0, 1, length.get() / 2, length.get()] {
+                let values = perm.first_k_sorted(k).unwrap();
+                assert_eq!(values.len(), k as usize);
+                assert!(values.windows(2).all(|w| w[0] < w[1]));
+                assert!(values.iter().all(|&x| x < length.get()));
+
+                let expected: BTreeSet<u32> = perm.take_iter(k).collect();
+                assert!(values.iter().eq(expected.iter()));
+            }
+            assert!(perm.first_k_sorted(length.get() + 1).is_err());
+        }
+    }
+
+    #[test]
+    fn test_unzip() {
+        let (lengths, seeds) = lengths_and_seeds();
+
+        for (&length, seed) in lengths.iter().zip(seeds) {
+            let perm = HashedPermutation::new_with_seed(length, seed);
+            let (inputs, outputs) = perm.unzip();
+            assert_eq!(inputs, (0..length.get()).collect::<Vec<u32>>());
+            assert_eq!(outputs, Vec::from(&perm));
+            assert!(is_permutation(outputs, length.get()));
+        }
+    }
+
+    #[test]
+    fn test_shuffle_ref() {
+        let (lengths, seeds) = lengths_and_seeds();
+
+        for (&length, seed) in lengths.iter().zip(seeds) {
+            let perm = HashedPermutation::new_with_seed(length, seed);
+            let inputs: Vec<u32> = (0..length.get()).collect();
+            let outputs: Vec<u32> = inputs
+                .iter()
+                .map(|i| perm.shuffle_ref(i))
+                .collect::<PermutationResult<_>>()
+                .unwrap();
+            assert_eq!(outputs, Vec::from(&perm));
+            assert!(perm.shuffle_ref(&length.get()).is_err());
+        }
+    }
+
+    #[test]
+    fn test_shuffle_clamped() {
+        let (lengths, seeds) = lengths_and_seeds();
+
+        for (&length, seed) in lengths.iter().zip(seeds) {
+            let perm = HashedPermutation::new_with_seed(length, seed);
+            let last = perm.shuffle(length.get() - 1).unwrap();
+
+            for i in 0..length.get() {
+                assert_eq!(perm.shuffle_clamped(i), perm.shuffle(i).unwrap());
+            }
+            assert_eq!(perm.shuffle_clamped(length.get()), last);
+            assert_eq!(perm.shuffle_clamped(u32::MAX), last);
+        }
+    }
+
+    #[test]
+    // Every row and every column should hold exactly one entry.
+    fn test_to_sparse_matrix() {
+        let (lengths, seeds) = lengths_and_seeds();
+
+        for (&length, seed) in lengths.iter().zip(seeds) {
+            let perm = HashedPermutation::new_with_seed(length, seed);
+            let entries = perm.to_sparse_matrix();
+            assert_eq!(entries.len(), length.get() as usize);
+
+            let rows: HashSet<u32> = entries.iter().map(|&(row, _)| row).collect();
+            let cols: HashSet<u32> = entries.iter().map(|&(_, col)| col).collect();
+            assert_eq!(rows.len(), length.get() as usize);
+            assert_eq!(cols.len(), length.get() as usize);
+
+            for (row, col) in entries {
+                assert!(row < length.get() && col < length.get());
+                assert_eq!(perm.shuffle(row).unwrap(), col);
+            }
+        }
+    }
+
+    #[test]
+    // The buckets should be balanced to within one input of each other.
+    fn test_bucket() {
+        let (lengths, seeds) = lengths_and_seeds();
+
+        for (&length, seed) in lengths.iter().zip(seeds) {
+            let perm = HashedPermutation::new_with_seed(length, seed);
+
+            for &buckets in &[1, 2, 4, 5, 7] {
+                let mut sizes = vec![0_u32; buckets as usize];
+
+                for i in 0..length.get() {
+                    sizes[perm.bucket(i, buckets).unwrap() as usize] += 1;
+                }
+                let min = *sizes.iter().min().unwrap();
+                let max = *sizes.iter().max().unwrap();
+                assert!(max - min <= 1);
+
+                if length.get() % buckets == 0 {
+                    assert!(sizes.iter().all(|&size| size == length.get() / buckets));
+                }
+            }
+            assert!(perm.bucket(length.get(), 2).is_err());
+        }
+    }
+
+    #[test]
+    fn test_bucket_zero() {
+        let perm = HashedPermutation::new_with_seed(NonZeroU32::new(10).unwrap(), 0);
+        assert!(matches!(
+            perm.bucket(0, 0),
+            Err(PermutationError::ZeroBuckets)
+        ));
+    }
+
+    #[test]
+    fn test_smallest_seed_mapping() {
+        let length = NonZeroU32::new(10).unwrap();
+        let seed = HashedPermutation::smallest_seed_mapping(length, 3, 0, 1000).unwrap();
+        assert_eq!(
+            HashedPermutation::new_with_seed(length, seed)
+                .shuffle(3)
+                .unwrap(),
+            0
+        );
+        assert!(
+            (0..seed).all(|s| HashedPermutation::new_with_seed(length, s)
+                .shuffle(3)
+                .unwrap()
+                != 0)
+        );
+        assert_eq!(
+            HashedPermutation::smallest_seed_mapping(length, 3, 0, seed),
+            Some(seed)
+        );
+
+        assert_eq!(
+            HashedPermutation::smallest_seed_mapping(length, 10, 0, 1000),
+            None
+        );
+        assert_eq!(
+            HashedPermutation::smallest_seed_mapping(length, 3, 10, 1000),
+            None
+        );
+    }
+
+    #[test]
+    fn test_boxed_iter() {
+        let (lengths, seeds) = lengths_and_seeds();
+
+        for (&length, seed) in lengths.iter().zip(seeds) {
+            let perm = HashedPermutation::new_with_seed(length, seed);
+            let iters: Vec<Box<dyn Iterator<Item = u32>>> =
+                vec![perm.boxed_iter(), Box::new(perm.iter())];
+
+            for it in iters {
+                let values: Vec<u32> = it.collect();
+                assert!(values.iter().copied().eq(perm.iter()));
+            }
+        }
+    }
+
+    #[test]
+    // A fixed index should land in many different places as the seed varies.
+    fn test_seed_sweep() {
+        let length = NonZeroU32::new(1000).unwrap();
+        let sweep: Vec<(u32, PermutationResult<u32>)> =
+            HashedPermutation::seed_sweep(length, 7, 0..100).collect();
+        assert_eq!(sweep.len(), 100);
+
+        let outputs: HashSet<u32> = sweep
+            .into_iter()
+            .map(|(seed, output)| {
+                let output = output.unwrap();
+                let perm = HashedPermutation::new_with_seed(length, seed);
+                assert_eq!(output, perm.shuffle(7).unwrap());
+                output
+            })
+            .collect();
+        assert!(outputs.len() > 80);
+
+        let mut out_of_range = HashedPermutation::seed_sweep(length, 1000, 0..);
+        assert!(out_of_range.next().unwrap().1.is_err());
+    }
+
+    #[test]
+    fn test_iter_with_state() {
+        let (lengths, seeds) = lengths_and_seeds();
+
+        for (&length, seed) in lengths.iter().zip(seeds) {
+            let perm = HashedPermutation::new_with_seed(length, seed);
+            let (outputs, rounds): (Vec<u32>, Vec<u32>) = perm.iter_with_state().unzip();
+            assert_eq!(outputs, Vec::from(&perm));
+            assert!(is_permutation(outputs, length.get()));
+            assert!(rounds.iter().sum::<u32>() >= length.get());
+
+            for (i, &round) in rounds.iter().enumerate() {
+                assert_eq!(perm.shuffle_with_stats(i as u32).unwrap().1, round);
+            }
+        }
+    }
+
+    #[test]
+    fn test_shuffle_rotated() {
+        let (lengths, seeds) = lengths_and_seeds();
+
+        for (&length, seed) in lengths.iter().zip(seeds) {
+            let perm = HashedPermutation::new_with_seed(length, seed);
+
+            for &offset in &[0, 1, 7, length.get() - 1, length.get(), u32::MAX] {
+                let outputs = (0..length.get()).map(|i| perm.shuffle_rotated(i, offset).unwrap());
+                assert!(is_permutation(outputs, length.get()));
+            }
+            assert_eq!(
+                perm.shuffle_rotated(0, length.get()).unwrap(),
+                perm.shuffle(0).unwrap()
+            );
+            assert!(perm.shuffle_rotated(length.get(), 0).is_err());
+        }
+    }
+
+    #[test]
+    fn test_find_permuted() {
+        let (lengths, seeds) = lengths_and_seeds();
+
+        for (&length, seed) in lengths.iter().zip(seeds) {
+            let perm = HashedPermutation::new_with_seed(length, seed);
+            let first_even = perm.find_permuted(|x| x % 2 == 0).unwrap();
+            assert_eq!(first_even % 2, 0);
+
+            let position = perm.iter().position(|x| x == first_even).unwrap();
+            assert!(perm.take_iter(position as u32).all(|x| x % 2 != 0));
+            assert_eq!(perm.find_permuted(|x| x >= length.get()), None);
+        }
+    }
+
+    #[test]
+    fn test_shuffle_wide() {
+        let (lengths, seeds) = lengths_and_seeds();
+
+        for (&length, seed) in lengths.iter().zip(seeds) {
+            let perm = HashedPermutation::new_with_seed(length, seed);
+
+            for i in 0..length.get() {
+                assert_eq!(
+                    perm.shuffle_wide(i).unwrap(),
+                    u64::from(perm.shuffle(i).unwrap())
+                );
+            }
+            assert!(perm.shuffle_wide(length.get()).is_err());
+        }
+    }
+
+    #[test]
+    fn test_from_parts() {
+        let (lengths, seeds) = lengths_and_seeds();
+
+        for (&length, seed) in lengths.iter().zip(seeds) {
+            let perm = HashedPermutation::from_parts(seed, length.get()).unwrap();
+            assert_eq!(perm.seed, seed);
+            assert_eq!(perm.length, length);
+        }
+        assert!(matches!(
+            HashedPermutation::from_parts(1234, 0),
+            Err(PermutationError::ZeroLength)
+        ));
+    }
+
+    #[test]
+    fn test_validate_range() {
+        let perm = HashedPermutation::new_with_seed(NonZeroU32::new(10).unwrap(), 1234);
+
+        for &(start, end) in &[(0, 0), (0, 10), (3, 7), (10, 10), (5, 5)] {
+            assert!(perm.validate_range(start, end).is_ok());
+        }
+        for &(start, end) in &[(0, 11), (7, 3), (11, 11), (11, 10), (0, u32::MAX)] {
+            assert!(matches!(
+                perm.validate_range(start, end),
+                Err(PermutationError::RangeOutOfBounds { start: s, end: e, length: 10 })
+                    if s == start && e == end
+            ));
+        }
+    }
+
+    #[test]
+    // Every voxel of the grid should be hit exactly once.
+    fn test_shuffle_3d() {
+        let (width, height, depth) = (4, 3, 5);
+        let length = NonZeroU32::new(width * height * depth).unwrap();
+
+        for seed in 0..8 {
+            let perm = HashedPermutation::new_with_seed(length, seed);
+            let mut seen = HashSet::new();
+
+            for z in 0..depth {
+                for y in 0..height {
+                    for x in 0..width {
+                        let (sx, sy, sz) = perm.shuffle_3d(x, y, z, width, height).unwrap();
+                        assert!(sx < width && sy < height && sz < depth);
+                        assert!(seen.insert((sx, sy, sz)));
+                    }
+                }
+            }
+            assert_eq!(seen.len() as u32, length.get());
+            assert!(perm.shuffle_3d(0, 0, depth, width, height).is_err());
+            assert!(perm.shuffle_3d(width, 0, 0, width, height).is_err());
+            assert!(perm.shuffle_3d(0, height, 0, width, height).is_err());
+            assert!(perm.shuffle_3d(0, 0, 0, 0, 0).is_err());
+            assert!(perm.shuffle_3d(0, 0, u32::MAX, width, height).is_err());
+            assert!(perm.shuffle_3d(0, 0, u32::MAX, u32::MAX, u32::MAX).is_err());
+        }
+    }
+
+    #[test]
+    // Any number of constant rounds should give the same bijection as `shuffle`.
+    fn test_shuffle_constant_rounds() {
+        let (lengths, seeds) = lengths_and_seeds();
+
+        for (&length, seed) in lengths.iter().zip(seeds) {
+            let perm = HashedPermutation::new_with_seed(length, seed);
+
+            for &rounds in &[0, 1, 2, 4, 16] {
+                let outputs: Vec<u32> = (0..length.get())
+                    .map(|i| perm.shuffle_constant_rounds(i, rounds).unwrap())
+                    .collect();
+                assert_eq!(outputs, Vec::from(&perm));
+                assert!(is_permutation(outputs, length.get()));
+            }
+            assert!(perm.shuffle_constant_rounds(length.get(), 4).is_err());
+        }
+    }
+
+    #[test]
+    // Stepping through the range from every offset should cover each input exactly once.
+    fn test_range_step_iter() {
+        let (lengths, seeds) = lengths_and_seeds();
+
+        for (&length, seed) in lengths.iter().zip(seeds) {
+            let perm = HashedPermutation::new_with_seed(length, seed);
+            let n = length.get();
+            let (start, end) = (n / 4, n - n / 4);
+            let step = 3;
+            let mut covered = Vec::new();
+
+            for offset in 0..step {
+                let stripe: Vec<u32> = perm
+                    .range_step_iter((start + offset).min(end), end, step)
+                    .unwrap()
+                    .collect();
+                let expected: Vec<u32> = (start + offset..end)
+                    .step_by(step as usize)
+                    .map(|i| perm.shuffle(i).unwrap())
+                    .collect();
+                assert_eq!(stripe, expected);
+                covered.extend(stripe);
+            }
+            covered.sort_unstable();
+            let mut expected: Vec<u32> = perm.range_iter(start, end).unwrap().collect();
+            expected.sort_unstable();
+            assert_eq!(covered, expected);
+        }
+        let perm = HashedPermutation::new_with_seed(NonZeroU32::new(10).unwrap(), 0);
+        assert!(matches!(
+            perm.range_step_iter(0, 10, 0),
+            Err(PermutationError::ZeroStep)
+        ));
+        assert!(perm.range_step_iter(0, 11, 1).is_err());
+        assert!(perm.range_step_iter(5, 4, 1).is_err());
+    }
+
+    #[test]
+    fn test_range_iter_rev() {
+        let (lengths, seeds) = lengths_and_seeds();
+
+        for (&length, seed) in lengths.iter().zip(seeds) {
+            let perm = HashedPermutation::new_with_seed(length, seed);
+            let n = length.get();
+
+            for &(start, end) in &[(0, n), (1, n / 2), (n / 2, n / 2), (n - 1, n)] {
+                let mut forward: Vec<u32> = perm.range_iter(start, end).unwrap().collect();
+                forward.reverse();
+                let backward: Vec<u32> = perm.range_iter_rev(start, end).unwrap().collect();
+                assert_eq!(backward, forward);
+            }
+            assert!(perm.range_iter_rev(1, 0).is_err());
+            assert!(perm.range_iter_rev(0, n + 1).is_err());
+        }
+    }
+
+    #[test]
+    fn test_with_seed_fn() {
+        let length = NonZeroU32::new(100).unwrap();
+        let mut calls = 0;
+        let perm = HashedPermutation::with_seed_fn(length, || {
+            calls += 1;
+            1234
+        });
+        assert_eq!(calls, 1);
+        assert_eq!(perm.seed, 1234);
+        assert_eq!(perm.length, length);
     }
 }
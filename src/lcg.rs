@@ -0,0 +1,129 @@
+//! A permutation based on a linear congruential generator, for comparing against Kensler's.
+
+use crate::domain::mix32;
+use crate::error::{PermutationError, PermutationResult};
+use crate::Permute;
+use std::num::NonZeroU32;
+
+/// A permutation that maps `i` to `(multiplier * i + increment) % length`.
+///
+/// As long as the multiplier is coprime with the length, this is a bijection over `0..length`.
+/// It's cheaper than [`HashedPermutation`](crate::HashedPermutation), but it mixes far worse:
+/// consecutive inputs are always mapped a constant stride apart. It's mostly useful as a baseline
+/// for judging the quality of the hashed permutation through the [`Permute`] trait.
+#[derive(Clone, Debug)]
+pub struct LcgPermutation {
+    /// The number of indices that are shuffled
+    length: NonZeroU32,
+
+    /// The multiplier, which is always coprime with the length
+    multiplier: u32,
+
+    /// The offset that is added after multiplying
+    increment: u32,
+}
+
+impl LcgPermutation {
+    /// Create a new LCG permutation over `0..length`, deriving its parameters from `seed`.
+    ///
+    /// The multiplier is picked by hashing the seed and stepping forwards to the nearest value
+    /// that is coprime with the length, and the increment is the seed reduced modulo the length.
+    pub fn new_with_seed(length: NonZeroU32, seed: u32) -> Self {
+        let n = length.get();
+        let mut multiplier = mix32(seed) % n;
+
+        while gcd(multiplier, n) != 1 {
+            multiplier = (multiplier + 1) % n;
+        }
+        Self {
+            length,
+            multiplier,
+            increment: seed % n,
+        }
+    }
+
+    /// Shuffle or permute a particular value.
+    ///
+    /// This returns an error if `input` is not within `0..length`.
+    pub fn shuffle(&self, input: u32) -> PermutationResult<u32> {
+        let n = self.length.get();
+
+        if input >= n {
+            return Err(PermutationError::ShuffleOutOfRange {
+                shuffle: input,
+                max_shuffle: n,
+            });
+        }
+        let output = (u64::from(self.multiplier) * u64::from(input) + u64::from(self.increment))
+            % u64::from(n);
+        Ok(output as u32)
+    }
+}
+
+impl Permute for LcgPermutation {
+    fn shuffle(&self, input: u32) -> PermutationResult<u32> {
+        LcgPermutation::shuffle(self, input)
+    }
+
+    fn len(&self) -> u32 {
+        self.length.get()
+    }
+}
+
+/// The greatest common divisor of two integers, where `gcd(0, n)` is `n`
+fn gcd(mut a: u32, mut b: u32) -> u32 {
+    while b != 0 {
+        let r = a % b;
+        a = b;
+        b = r;
+    }
+    a
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::edge_lengths_and_seeds;
+    use crate::HashedPermutation;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_bijection() {
+        let (lengths, seeds) = edge_lengths_and_seeds();
+
+        for (&length, seed) in lengths.iter().zip(seeds) {
+            let perm = LcgPermutation::new_with_seed(length, seed);
+            let length = length.get();
+            assert_eq!(gcd(perm.multiplier, length), 1);
+            let mut set = HashSet::with_capacity(length as usize);
+
+            for i in 0..length {
+                let res = perm.shuffle(i).unwrap();
+                assert!(res < length);
+                assert!(set.insert(res));
+            }
+            assert_eq!(set.len(), length as usize);
+            assert!(perm.shuffle(length).is_err());
+        }
+    }
+
+    #[test]
+    // Both backends should be usable through the common trait.
+    fn test_permute_trait() {
+        fn outputs<P: Permute>(perm: &P) -> HashSet<u32> {
+            (0..perm.len()).map(|i| perm.shuffle(i).unwrap()).collect()
+        }
+        let length = NonZeroU32::new(100).unwrap();
+        let lcg = LcgPermutation::new_with_seed(length, 1234);
+        let hashed = HashedPermutation::new_with_seed(length, 1234);
+        assert_eq!(outputs(&lcg), outputs(&hashed));
+        assert!(!lcg.is_empty());
+    }
+
+    #[test]
+    fn test_gcd() {
+        assert_eq!(gcd(0, 7), 7);
+        assert_eq!(gcd(12, 18), 6);
+        assert_eq!(gcd(17, 5), 1);
+    }
+}
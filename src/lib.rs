@@ -43,11 +43,71 @@
 //!     println!("{}", i);
 //! }
 //! ```
+//!
+//! # Features
+//!
+//! - `use-rand`: adds constructors that pick a random seed with `rand`, such as
+//!   `HashedPermutation::new` and `HashedIter::new`, as well as `PermutationSampler`. Without
+//!   this feature those constructors don't exist, so you need to supply a seed yourself with
+//!   `HashedPermutation::new_with_seed` or `HashedIter::new_with_seed`.
+//! - `getrandom`: adds `HashedPermutation::new`, seeded straight from the operating system with
+//!   `getrandom` instead of `rand`. If `use-rand` is also enabled, its constructor is used instead.
+//! - `serde`: implements `Serialize` and `Deserialize` for `HashedPermutation` and `HashedIter`.
+//! - `ndarray`: adds `permute_axis` for permuting the slices of an `ndarray` array.
+//! - `rayon`: adds parallel helpers such as `HashedPermutation::iter_chunked_parallel`.
+
+// The test fixtures are built with `vec!`, which clippy would rather see as arrays.
+#![cfg_attr(test, allow(clippy::useless_vec))]
+
+#[macro_use]
+mod macros;
 
+mod block;
+mod chars;
+mod compare;
+mod cycles;
+mod domain;
 mod error;
+mod filtered;
+mod inverse;
 mod iterator;
 mod kensler;
+mod lcg;
+#[cfg(feature = "ndarray")]
+mod ndarray_ext;
+#[cfg(feature = "rayon")]
+mod parallel;
+mod permute;
+mod reflect;
+mod relabel;
+mod reorder;
+#[cfg(feature = "use-rand")]
+mod sampler;
+mod sequences;
+mod static_permutation;
+mod stats;
+#[cfg(test)]
+mod test_utils;
+mod unique_sampler;
 
+pub use block::BlockPermutation;
+pub use chars::permute_chars;
+pub use cycles::Parity;
+pub use domain::Domain;
 pub use error::{PermutationError, PermutationResult};
-pub use iterator::HashedIter;
-pub use kensler::HashedPermutation;
+pub use filtered::FilteredPermutation;
+pub use inverse::InversePermutation;
+pub use iterator::{HashedIter, HashedRangeIter, PermutationIter};
+pub use kensler::{HashedPermutation, HashedPermutation16};
+pub use lcg::LcgPermutation;
+#[cfg(feature = "ndarray")]
+pub use ndarray_ext::permute_axis;
+pub use permute::Permute;
+pub use reflect::ReflectedPermutation;
+pub use relabel::RelabeledPermutation;
+#[cfg(feature = "use-rand")]
+pub use sampler::PermutationSampler;
+pub use sequences::bit_reverse;
+pub use static_permutation::StaticPermutation;
+pub use stats::{SeedQuality, VerificationReport};
+pub use unique_sampler::UniqueSampler;
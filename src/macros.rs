@@ -0,0 +1,81 @@
+//! Macros for constructing permutations with less boilerplate.
+
+/// Construct a [`HashedPermutation`](crate::HashedPermutation) from a constant length and a seed.
+///
+/// The length is checked at compile time, so there is no need to unwrap a `NonZeroU32` by hand,
+/// and the macro can be used to initialize a `const` or `static`. The length must be a constant
+/// expression, while the seed can be any `u32` expression.
+///
+/// ```
+/// use hashed_permutation::{hashed_permutation, HashedPermutation};
+///
+/// static PERM: HashedPermutation = hashed_permutation!(length = 10, seed = 1234);
+/// assert_eq!(PERM.length.get(), 10);
+/// assert_eq!(PERM.seed, 1234);
+/// ```
+///
+/// A length of zero is rejected at compile time:
+///
+/// ```compile_fail
+/// # use hashed_permutation::hashed_permutation;
+/// let perm = hashed_permutation!(length = 0, seed = 1234);
+/// ```
+#[macro_export]
+macro_rules! hashed_permutation {
+    (length = $length:expr, seed = $seed:expr $(,)?) => {{
+        const LENGTH: ::core::num::NonZeroU32 = match ::core::num::NonZeroU32::new($length) {
+            ::core::option::Option::Some(length) => length,
+            ::core::option::Option::None => {
+                ::core::panic!("the length of a permutation must be nonzero")
+            }
+        };
+        $crate::HashedPermutation {
+            length: LENGTH,
+            seed: $seed,
+        }
+    }};
+}
+
+#[cfg(test)]
+mod test {
+    use crate::HashedPermutation;
+    use std::num::NonZeroU32;
+
+    #[test]
+    // The macro should build the same permutation as the constructor.
+    fn test_hashed_permutation_macro() {
+        const PERM: HashedPermutation = hashed_permutation!(length = 100, seed = 5);
+        let seed = 249;
+        let perm = hashed_permutation!(length = 13, seed = seed,);
+
+        let expected = HashedPermutation::new_with_seed(NonZeroU32::new(100).unwrap(), 5);
+        assert!(PERM.iter().eq(expected.iter()));
+        let expected = HashedPermutation::new_with_seed(NonZeroU32::new(13).unwrap(), seed);
+        assert!(perm.iter().eq(expected.iter()));
+    }
+
+    #[test]
+    // The macro shouldn't depend on anything being in scope where it's called.
+    fn test_hashed_permutation_macro_hygiene() {
+        let perm = hygiene::permutation();
+        assert_eq!(perm.length.get(), 10);
+        assert_eq!(perm.seed, 1234);
+    }
+
+    mod hygiene {
+        #![no_implicit_prelude]
+
+        // A macro named `panic` at the call site would shadow an unqualified `panic!` inside of
+        // `hashed_permutation!`.
+        #[allow(unused_macros)]
+        macro_rules! panic {
+            ($($tt:tt)*) => {
+                ::core::compile_error!("`hashed_permutation!` called the wrong `panic!`")
+            };
+        }
+
+        pub fn permutation() -> crate::HashedPermutation {
+            hashed_permutation!(length = 10, seed = 1234)
+        }
+    }
+}
@@ -0,0 +1,74 @@
+//! Integration with the [`ndarray`](https://docs.rs/ndarray) crate.
+
+use crate::HashedPermutation;
+use ndarray::{ArrayViewMut, Axis, RemoveAxis};
+use std::convert::TryFrom;
+use std::num::NonZeroU32;
+
+/// Permute the slices of an array along an axis.
+///
+/// The slice at index `i` along `axis` is moved to index `shuffle(i)`, where the permutation is a
+/// `HashedPermutation` over the length of the axis with the given seed. Empty axes are left
+/// untouched.
+///
+/// This copies the array once so the slices can be moved around without overwriting each other.
+///
+/// ```
+/// # use hashed_permutation::permute_axis;
+/// use ndarray::{array, Axis};
+///
+/// let mut arr = array![[0, 1], [2, 3], [4, 5]];
+/// permute_axis(&mut arr.view_mut(), Axis(0), 1234);
+/// ```
+///
+/// # Panics
+///
+/// Panics if `axis` is out of bounds for the array, or if the length of the axis doesn't fit in a
+/// `u32`.
+pub fn permute_axis<A, D>(arr: &mut ArrayViewMut<A, D>, axis: Axis, seed: u32)
+where
+    A: Clone,
+    D: RemoveAxis,
+{
+    let length = u32::try_from(arr.len_of(axis)).expect("the length of the axis must fit in a u32");
+    let length = match NonZeroU32::new(length) {
+        Some(length) => length,
+        None => return,
+    };
+    let perm = HashedPermutation::new_with_seed(length, seed);
+    let original = arr.to_owned();
+
+    for (i, lane) in original.axis_iter(axis).enumerate() {
+        let target = perm.permute(i as u32);
+        arr.index_axis_mut(axis, target as usize).assign(&lane);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ndarray::Array2;
+
+    #[test]
+    // Every row should end up in the position dictated by the permutation, and each row should
+    // appear exactly once.
+    fn test_permute_rows() {
+        let rows = 13;
+        let cols = 4;
+        let seed = 100;
+        let original = Array2::from_shape_fn((rows, cols), |(r, c)| r * cols + c);
+        let mut arr = original.clone();
+        permute_axis(&mut arr.view_mut(), Axis(0), seed);
+
+        let perm = HashedPermutation::new_with_seed(NonZeroU32::new(rows as u32).unwrap(), seed);
+        let mut seen = vec![false; rows];
+
+        for (i, row) in original.outer_iter().enumerate() {
+            let target = perm.shuffle(i as u32).unwrap() as usize;
+            assert_eq!(arr.row(target), row);
+            assert!(!seen[target]);
+            seen[target] = true;
+        }
+        assert!(seen.iter().all(|&x| x));
+    }
+}
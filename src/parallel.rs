@@ -0,0 +1,108 @@
+//! Parallel helpers for materializing permutations with [`rayon`](https://docs.rs/rayon).
+
+use crate::error::{PermutationError, PermutationResult};
+use crate::HashedPermutation;
+use rayon::prelude::*;
+
+impl HashedPermutation {
+    /// Create a parallel iterator over fixed-size chunks of the permutation.
+    ///
+    /// Chunk `k` holds the shuffles of the inputs in `k * chunk_size..(k + 1) * chunk_size`, so
+    /// every chunk is `chunk_size` elements long except possibly the last one. The iterator is
+    /// indexed, so collecting it keeps the chunks in order, and concatenating them reproduces the
+    /// sequential permutation.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `chunk_size` is zero.
+    pub fn iter_chunked_parallel(
+        &self,
+        chunk_size: u32,
+    ) -> impl IndexedParallelIterator<Item = Vec<u32>> + '_ {
+        assert!(chunk_size > 0, "the chunk size must be greater than zero");
+        let n = self.length.get();
+        // `n` is nonzero, so this is the ceiling of `n / chunk_size` without overflowing
+        let chunks = (n - 1) / chunk_size + 1;
+
+        (0..chunks).into_par_iter().map(move |k| {
+            let start = k * chunk_size;
+            let end = start.saturating_add(chunk_size).min(n);
+            (start..end).map(|i| self.permute(i)).collect()
+        })
+    }
+
+    /// Shuffle a slice of arbitrary inputs in parallel, writing `shuffle(inputs[k])` to `out[k]`.
+    ///
+    /// This returns an error if `out` doesn't have the same length as `inputs`, in which case
+    /// nothing is written, or if any input isn't within `0..length`. The inputs are processed
+    /// concurrently, so if several of them are out of range, it's unspecified which one the error
+    /// reports, and `out` may have been partially written.
+    pub fn shuffle_slice_par(&self, inputs: &[u32], out: &mut [u32]) -> PermutationResult<()> {
+        if inputs.len() != out.len() {
+            return Err(PermutationError::LengthMismatch {
+                expected: inputs.len(),
+                found: out.len(),
+            });
+        }
+        inputs
+            .par_iter()
+            .zip(out.par_iter_mut())
+            .try_for_each(|(&input, output)| {
+                *output = self.shuffle(input)?;
+                Ok(())
+            })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::edge_lengths_and_seeds;
+    use std::num::NonZeroU32;
+
+    #[test]
+    // Flattening the chunks in order should give back the sequential permutation.
+    fn test_iter_chunked_parallel() {
+        let (lengths, seeds) = edge_lengths_and_seeds();
+
+        for (&length, seed) in lengths.iter().zip(seeds) {
+            let perm = HashedPermutation::new_with_seed(length, seed);
+            let length = length.get();
+
+            for &chunk_size in &[1, 7, 64, length, length + 1] {
+                let chunks: Vec<Vec<u32>> = perm.iter_chunked_parallel(chunk_size).collect();
+                assert!(chunks
+                    .iter()
+                    .all(|c| !c.is_empty() && c.len() as u32 <= chunk_size));
+                assert!(chunks.into_iter().flatten().eq(perm.iter()));
+            }
+        }
+    }
+
+    #[test]
+    // The parallel shuffle should match shuffling each input sequentially.
+    fn test_shuffle_slice_par() {
+        let (lengths, seeds) = edge_lengths_and_seeds();
+
+        for (&length, seed) in lengths.iter().zip(seeds) {
+            let perm = HashedPermutation::new_with_seed(length, seed);
+            let length = length.get();
+            // Scramble the inputs and repeat some of them, so they aren't a contiguous range
+            let inputs: Vec<u32> = (0..2 * length).map(|i| (i * 7 + 3) % length).collect();
+            let expected: Vec<u32> = inputs.iter().map(|&i| perm.shuffle(i).unwrap()).collect();
+            let mut out = vec![0; inputs.len()];
+            perm.shuffle_slice_par(&inputs, &mut out).unwrap();
+            assert_eq!(out, expected);
+
+            assert!(perm.shuffle_slice_par(&inputs, &mut out[1..]).is_err());
+            assert!(perm.shuffle_slice_par(&[0, length], &mut [0, 0]).is_err());
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_iter_chunked_parallel_zero() {
+        let perm = HashedPermutation::new_with_seed(NonZeroU32::new(10).unwrap(), 0);
+        let _ = perm.iter_chunked_parallel(0);
+    }
+}
@@ -0,0 +1,100 @@
+//! A common interface for the different permutation backends.
+
+use crate::error::{PermutationError, PermutationResult};
+use crate::{HashedPermutation, InversePermutation, StaticPermutation};
+
+/// A bijection over `0..len()` that can shuffle individual indices on demand.
+///
+/// This abstracts over the different permutation algorithms in this crate, so code can be written
+/// once and run with any of them, for example to compare their quality.
+pub trait Permute {
+    /// Shuffle or permute a particular value.
+    ///
+    /// This returns an error if `input` is not within `0..len()`.
+    fn shuffle(&self, input: u32) -> PermutationResult<u32>;
+
+    /// The number of indices the permutation shuffles
+    fn len(&self) -> u32;
+
+    /// Whether the permutation has no indices to shuffle
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Permute for HashedPermutation {
+    fn shuffle(&self, input: u32) -> PermutationResult<u32> {
+        HashedPermutation::shuffle(self, input)
+    }
+
+    fn len(&self) -> u32 {
+        self.length.get()
+    }
+}
+
+impl<const LEN: u32> Permute for StaticPermutation<LEN> {
+    fn shuffle(&self, input: u32) -> PermutationResult<u32> {
+        if input >= LEN {
+            return Err(PermutationError::ShuffleOutOfRange {
+                shuffle: input,
+                max_shuffle: LEN,
+            });
+        }
+        Ok(StaticPermutation::shuffle(self, input))
+    }
+
+    fn len(&self) -> u32 {
+        LEN
+    }
+}
+
+impl Permute for InversePermutation {
+    fn shuffle(&self, input: u32) -> PermutationResult<u32> {
+        self.apply(input)
+    }
+
+    fn len(&self) -> u32 {
+        self.table.len() as u32
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::LcgPermutation;
+    use std::collections::HashSet;
+    use std::num::NonZeroU32;
+
+    /// Shuffle every index of a permutation through a trait object
+    fn shuffle_all(perm: &dyn Permute) -> Vec<u32> {
+        (0..perm.len()).map(|i| perm.shuffle(i).unwrap()).collect()
+    }
+
+    #[test]
+    // Every backend should be a bijection over `0..len()` when used through `&dyn Permute`, and
+    // should reject the first index past the end.
+    fn test_dyn_permute() {
+        let length = NonZeroU32::new(100).unwrap();
+        let hashed = HashedPermutation::new_with_seed(length, 1234);
+        let backends: Vec<Box<dyn Permute>> = vec![
+            Box::new(hashed.clone()),
+            Box::new(hashed.inverse()),
+            Box::new(StaticPermutation::<100>::new(1234)),
+            Box::new(LcgPermutation::new_with_seed(length, 1234)),
+        ];
+
+        for perm in &backends {
+            assert_eq!(perm.len(), length.get());
+            assert!(!perm.is_empty());
+            let values = shuffle_all(perm.as_ref());
+            let set: HashSet<u32> = values.iter().copied().collect();
+            assert_eq!(set, (0..length.get()).collect());
+            assert!(perm.shuffle(length.get()).is_err());
+        }
+        // The static and dynamic Kensler permutations should agree exactly
+        assert_eq!(
+            shuffle_all(backends[0].as_ref()),
+            shuffle_all(backends[2].as_ref())
+        );
+    }
+}
@@ -0,0 +1,81 @@
+//! A permutation whose outputs are mirrored.
+
+use crate::error::PermutationResult;
+use crate::{HashedPermutation, Permute};
+
+/// A hashed permutation whose outputs are reflected across the middle of `0..length`.
+///
+/// This is created with [`HashedPermutation::reflect`]. Shuffling `input` gives
+/// `length - 1 - permutation.shuffle(input)`, so it's the complementary ordering of the original
+/// permutation and needs no extra memory.
+#[derive(Clone, Debug)]
+pub struct ReflectedPermutation {
+    /// The permutation whose outputs are reflected
+    permutation: HashedPermutation,
+}
+
+impl HashedPermutation {
+    /// Mirror the outputs of this permutation.
+    ///
+    /// ```
+    /// # use hashed_permutation::HashedPermutation;
+    /// use std::num::NonZeroU32;
+    ///
+    /// let perm = HashedPermutation::new_with_seed(NonZeroU32::new(10).unwrap(), 1234);
+    /// let reflected = perm.reflect();
+    /// assert_eq!(reflected.shuffle(0).unwrap(), 9 - perm.shuffle(0).unwrap());
+    /// ```
+    pub fn reflect(&self) -> ReflectedPermutation {
+        ReflectedPermutation {
+            permutation: self.clone(),
+        }
+    }
+}
+
+impl ReflectedPermutation {
+    /// Shuffle a value and reflect the result.
+    ///
+    /// `input` must be within `0..length`.
+    pub fn shuffle(&self, input: u32) -> PermutationResult<u32> {
+        let output = self.permutation.shuffle(input)?;
+        Ok(self.permutation.length.get() - 1 - output)
+    }
+}
+
+impl Permute for ReflectedPermutation {
+    fn shuffle(&self, input: u32) -> PermutationResult<u32> {
+        ReflectedPermutation::shuffle(self, input)
+    }
+
+    fn len(&self) -> u32 {
+        self.permutation.length.get()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::edge_lengths_and_seeds;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_reflect() {
+        let (lengths, seeds) = edge_lengths_and_seeds();
+
+        for (&length, seed) in lengths.iter().zip(seeds) {
+            let perm = HashedPermutation::new_with_seed(length, seed);
+            let length = length.get();
+            let reflected = perm.reflect();
+            assert_eq!(Permute::len(&reflected), length);
+            let mut seen = HashSet::new();
+
+            for i in 0..length {
+                let res = reflected.shuffle(i).unwrap();
+                assert_eq!(res, length - 1 - perm.shuffle(i).unwrap());
+                assert!(seen.insert(res));
+            }
+            assert_eq!(seen.len(), length as usize);
+            assert!(reflected.shuffle(length).is_err());
+        }
+    }
+}
@@ -0,0 +1,110 @@
+//! A permutation whose outputs are relabeled by a user-supplied table.
+
+use crate::error::{PermutationError, PermutationResult};
+use crate::kensler::is_permutation;
+use crate::HashedPermutation;
+use std::mem;
+
+/// A hashed permutation composed with a relabeling of its outputs.
+///
+/// This is created with [`HashedPermutation::with_relabel`]. Shuffling `input` gives
+/// `labels[permutation.shuffle(input)]`, and since both steps are bijections over `0..length`, so
+/// is their composition.
+#[derive(Clone, Debug)]
+pub struct RelabeledPermutation {
+    /// The permutation that is applied first
+    permutation: HashedPermutation,
+
+    /// `labels[output]` is the label for an output of the permutation
+    labels: Vec<u32>,
+}
+
+impl HashedPermutation {
+    /// Compose this permutation with a relabeling of its outputs.
+    ///
+    /// `labels` must be a permutation of `0..length`. This returns an error if it has the wrong
+    /// length, or if it repeats a value or contains a value outside of `0..length`.
+    ///
+    /// ```
+    /// # use hashed_permutation::HashedPermutation;
+    /// use std::num::NonZeroU32;
+    ///
+    /// let perm = HashedPermutation::new_with_seed(NonZeroU32::new(3).unwrap(), 1234);
+    /// let relabeled = perm.with_relabel(vec![2, 0, 1]).unwrap();
+    /// let expected = [2, 0, 1][perm.shuffle(0).unwrap() as usize];
+    /// assert_eq!(relabeled.shuffle(0).unwrap(), expected);
+    /// ```
+    pub fn with_relabel(&self, labels: Vec<u32>) -> PermutationResult<RelabeledPermutation> {
+        let n = self.length.get();
+
+        if labels.len() != n as usize {
+            return Err(PermutationError::LengthMismatch {
+                expected: n as usize,
+                found: labels.len(),
+            });
+        }
+        if !is_permutation(labels.iter().copied(), n) {
+            return Err(PermutationError::NotAPermutation { length: n });
+        }
+        Ok(RelabeledPermutation {
+            permutation: self.clone(),
+            labels,
+        })
+    }
+}
+
+impl RelabeledPermutation {
+    /// The number of bytes the table of labels allocates on the heap
+    pub fn heap_bytes(&self) -> usize {
+        self.labels.capacity() * mem::size_of::<u32>()
+    }
+
+    /// Shuffle a value and relabel the result.
+    ///
+    /// `input` must be within `0..length`.
+    pub fn shuffle(&self, input: u32) -> PermutationResult<u32> {
+        let output = self.permutation.shuffle(input)?;
+        Ok(self.labels[output as usize])
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::edge_lengths_and_seeds;
+    use std::collections::HashSet;
+    use std::num::NonZeroU32;
+
+    #[test]
+    // Relabeling with a permutation should keep the result a bijection.
+    fn test_with_relabel() {
+        let (lengths, seeds) = edge_lengths_and_seeds();
+
+        for (&length, seed) in lengths.iter().zip(seeds) {
+            let perm = HashedPermutation::new_with_seed(length, seed);
+            let length = length.get();
+            // Reverse the outputs, which is a relabeling that is easy to check
+            let labels: Vec<u32> = (0..length).rev().collect();
+            let relabeled = perm.with_relabel(labels).unwrap();
+            let mut seen = HashSet::new();
+
+            for i in 0..length {
+                let res = relabeled.shuffle(i).unwrap();
+                assert_eq!(res, length - 1 - perm.shuffle(i).unwrap());
+                assert!(seen.insert(res));
+            }
+            assert_eq!(seen.len(), length as usize);
+            assert!(relabeled.shuffle(length).is_err());
+        }
+    }
+
+    #[test]
+    fn test_with_relabel_invalid() {
+        let perm = HashedPermutation::new_with_seed(NonZeroU32::new(4).unwrap(), 0);
+        assert!(perm.with_relabel(vec![0, 1, 2]).is_err());
+        assert!(perm.with_relabel(vec![0, 1, 2, 3, 4]).is_err());
+        assert!(perm.with_relabel(vec![0, 1, 1, 3]).is_err());
+        assert!(perm.with_relabel(vec![0, 1, 2, 4]).is_err());
+        assert!(perm.with_relabel(vec![3, 1, 2, 0]).is_ok());
+    }
+}
@@ -0,0 +1,110 @@
+//! Reordering slices of data with a permutation.
+
+use crate::error::{PermutationError, PermutationResult};
+use crate::HashedPermutation;
+
+impl HashedPermutation {
+    /// Get a gather kernel that reorders slices with this permutation.
+    ///
+    /// The returned closure takes a slice `src` and returns a new vector whose `i`-th element is
+    /// `src[shuffle(i)]`. The slice must have exactly `length` elements, otherwise the closure
+    /// returns an error. This keeps the permutation decoupled from the data it's applied to.
+    ///
+    /// ```
+    /// # use hashed_permutation::HashedPermutation;
+    /// use std::num::NonZeroU32;
+    ///
+    /// let perm = HashedPermutation::new_with_seed(NonZeroU32::new(3).unwrap(), 1234);
+    /// let gather = perm.gather_fn();
+    /// let gathered = gather(&['a', 'b', 'c']).unwrap();
+    /// assert_eq!(gathered[0], ['a', 'b', 'c'][perm.shuffle(0).unwrap() as usize]);
+    /// ```
+    pub fn gather_fn<T: Clone>(&self) -> impl Fn(&[T]) -> PermutationResult<Vec<T>> + '_ {
+        move |src| {
+            self.check_slice_len(src)?;
+            Ok(self
+                .unchecked_iter()
+                .map(|i| src[i as usize].clone())
+                .collect())
+        }
+    }
+
+    /// Scatter the elements of `src` to their shuffled positions.
+    ///
+    /// This is the inverse of gathering with [`HashedPermutation::gather_fn`]: the result is a new
+    /// vector where `dst[shuffle(i)]` is `src[i]`. The slice must have exactly `length` elements,
+    /// otherwise this returns an error.
+    pub fn scatter<T: Clone>(&self, src: &[T]) -> PermutationResult<Vec<T>> {
+        self.check_slice_len(src)?;
+        let mut dst = vec![None; src.len()];
+
+        for (elem, output) in src.iter().zip(self.unchecked_iter()) {
+            dst[output as usize] = Some(elem.clone());
+        }
+        Ok(dst
+            .into_iter()
+            .map(|elem| elem.expect("a bijection writes to every position"))
+            .collect())
+    }
+
+    /// Return an error if `slice` doesn't have exactly `length` elements
+    fn check_slice_len<T>(&self, slice: &[T]) -> PermutationResult<()> {
+        if slice.len() != self.length.get() as usize {
+            return Err(PermutationError::LengthMismatch {
+                expected: self.length.get() as usize,
+                found: slice.len(),
+            });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::edge_lengths_and_seeds;
+
+    #[test]
+    // Gathering should reorder the source, with each element read from its shuffled index.
+    fn test_gather_fn() {
+        let (lengths, seeds) = edge_lengths_and_seeds();
+
+        for (&length, seed) in lengths.iter().zip(seeds) {
+            let perm = HashedPermutation::new_with_seed(length, seed);
+            let length = length.get();
+            let src: Vec<u8> = (0..length).map(|i| i as u8).collect();
+            let gather = perm.gather_fn();
+            let gathered = gather(&src).unwrap();
+
+            for (i, &elem) in gathered.iter().enumerate() {
+                assert_eq!(elem, src[perm.shuffle(i as u32).unwrap() as usize]);
+            }
+            let mut sorted = gathered.clone();
+            sorted.sort_unstable();
+            let mut expected = src.clone();
+            expected.sort_unstable();
+            assert_eq!(sorted, expected);
+
+            assert!(gather(&src[1..]).is_err());
+        }
+    }
+
+    #[test]
+    // Scattering and then gathering should give back the original slice.
+    fn test_scatter() {
+        let (lengths, seeds) = edge_lengths_and_seeds();
+
+        for (&length, seed) in lengths.iter().zip(seeds) {
+            let perm = HashedPermutation::new_with_seed(length, seed);
+            let length = length.get();
+            let src: Vec<String> = (0..length).map(|i| i.to_string()).collect();
+            let scattered = perm.scatter(&src).unwrap();
+
+            for (i, elem) in src.iter().enumerate() {
+                assert_eq!(&scattered[perm.shuffle(i as u32).unwrap() as usize], elem);
+            }
+            assert_eq!(perm.gather_fn()(&scattered).unwrap(), src);
+            assert!(perm.scatter(&src[1..]).is_err());
+        }
+    }
+}
@@ -0,0 +1,122 @@
+//! Samplers that draw values from a permutation without replacement.
+
+use crate::HashedPermutation;
+use rand::Rng;
+use std::collections::HashMap;
+
+/// A stateful sampler that draws the values of a permutation in a random order, as part of a
+/// larger `rand` pipeline.
+///
+/// Each call to [`PermutationSampler::sample`] uses the RNG it's given to pick a random position
+/// of the permutation that hasn't been drawn yet, and returns the value at that position. Every
+/// index in `0..length` is drawn exactly once, after which it returns `None`.
+///
+/// The draws are a Fisher-Yates shuffle over the positions of the permutation that only stores
+/// the positions it has moved, so the sampler holds at most one entry per draw.
+///
+/// ```
+/// # use hashed_permutation::{HashedPermutation, PermutationSampler};
+/// use std::num::NonZeroU32;
+///
+/// let perm = HashedPermutation::new_with_seed(NonZeroU32::new(10).unwrap(), 1234);
+/// let mut sampler = PermutationSampler::new(perm);
+/// let mut rng = rand::thread_rng();
+///
+/// let draws: Vec<u32> = std::iter::from_fn(|| sampler.sample(&mut rng)).collect();
+/// assert_eq!(draws.len(), 10);
+/// ```
+#[derive(Clone, Debug)]
+pub struct PermutationSampler {
+    /// The permutation that values are drawn from
+    permutation: HashedPermutation,
+
+    /// The number of values that have been drawn so far
+    drawn: u32,
+
+    /// The positions in `drawn..length` that hold a different position than their own. Any
+    /// position that isn't in here still holds itself.
+    swapped: HashMap<u32, u32>,
+}
+
+impl PermutationSampler {
+    /// Create a new sampler that draws values from a permutation
+    pub fn new(permutation: HashedPermutation) -> Self {
+        Self {
+            permutation,
+            drawn: 0,
+            swapped: HashMap::new(),
+        }
+    }
+
+    /// Use `rng` to draw a random unused index, or `None` once every index has been drawn
+    pub fn sample<R: Rng + ?Sized>(&mut self, rng: &mut R) -> Option<u32> {
+        let length = self.permutation.length.get();
+
+        if self.drawn == length {
+            return None;
+        }
+        let pick = rng.gen_range(self.drawn..length);
+        let position = self.swapped.get(&pick).copied().unwrap_or(pick);
+        // The position at the front of the remaining ones is used up by this draw, so whatever it
+        // held moves into the slot that was just picked.
+        let front = self.swapped.remove(&self.drawn).unwrap_or(self.drawn);
+
+        if pick != self.drawn {
+            self.swapped.insert(pick, front);
+        }
+        self.drawn += 1;
+        Some(self.permutation.permute(position))
+    }
+
+    /// The number of values that have been drawn from the sampler so far
+    pub fn drawn(&self) -> u32 {
+        self.drawn
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use std::collections::HashSet;
+    use std::num::NonZeroU32;
+
+    #[test]
+    // Drawing `length` values from the sampler should hit every index exactly once, after which
+    // the sampler is exhausted.
+    fn test_bijection() {
+        let mut rng = rand::thread_rng();
+
+        for &length in &[1, 5, 13, 100, 249] {
+            let length = NonZeroU32::new(length).unwrap();
+            let perm = HashedPermutation::new_with_seed(length, length.get());
+            let mut sampler = PermutationSampler::new(perm);
+            let mut set = HashSet::with_capacity(length.get() as usize);
+
+            for _ in 0..length.get() {
+                let elem = sampler.sample(&mut rng).unwrap();
+                assert!(elem < length.get());
+                assert!(set.insert(elem));
+            }
+            assert_eq!(sampler.drawn(), length.get());
+            assert!(sampler.sample(&mut rng).is_none());
+            assert!(sampler.swapped.is_empty());
+        }
+    }
+
+    #[test]
+    // The order of the draws should come from the RNG passed to each draw, so the same RNG state
+    // draws the same values from the same permutation, and a different one usually doesn't.
+    fn test_draws_from_rng() {
+        let perm = HashedPermutation::new_with_seed(NonZeroU32::new(100).unwrap(), 1234);
+        let draw = |rng_seed| {
+            let mut sampler = PermutationSampler::new(perm.clone());
+            let mut rng = StdRng::seed_from_u64(rng_seed);
+            std::iter::from_fn(move || sampler.sample(&mut rng)).collect::<Vec<_>>()
+        };
+
+        assert_eq!(draw(1), draw(1));
+        assert_ne!(draw(1), draw(2));
+    }
+}
@@ -0,0 +1,62 @@
+//! Other low-discrepancy orderings that are useful to compare against the hashed permutation.
+
+/// Reverse the lowest `bits` bits of `input`.
+///
+/// This is the ordering used by the base-2 van der Corput sequence: iterating `bit_reverse(i,
+/// bits)` for `i` in `0..2^bits` visits every index in `0..2^bits` exactly once, in a
+/// low-discrepancy order. Only the lowest `bits` bits of `input` are used, and the result always
+/// fits in `bits` bits.
+///
+/// ```
+/// # use hashed_permutation::bit_reverse;
+/// assert_eq!(bit_reverse(0b0011, 4), 0b1100);
+/// assert_eq!(bit_reverse(0b1_0011, 4), 0b1100);
+/// ```
+///
+/// # Panics
+///
+/// Panics if `bits` is greater than 32.
+pub fn bit_reverse(input: u32, bits: u32) -> u32 {
+    assert!(bits <= 32, "cannot reverse more than 32 bits, got {}", bits);
+
+    if bits == 0 {
+        return 0;
+    }
+    // Reversing all 32 bits moves the bits we care about to the top of the integer, and shifting
+    // them back down discards the bits above `bits`.
+    input.reverse_bits() >> (32 - bits)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_involution() {
+        for bits in 0..=32 {
+            let mask = u32::MAX.checked_shr(32 - bits).unwrap_or(0);
+
+            for &x in &[0, 1, 2, 3, 5, 0xdead_beef, 0x8000_0000, u32::MAX] {
+                let reversed = bit_reverse(x, bits);
+                assert_eq!(reversed & !mask, 0);
+                assert_eq!(bit_reverse(reversed, bits), x & mask);
+            }
+        }
+    }
+
+    #[test]
+    // Bit-reversing every index in a power of two range should cover the range exactly once.
+    fn test_bijection() {
+        let bits = 8;
+        let mut result: Vec<u32> = (0..1 << bits).map(|x| bit_reverse(x, bits)).collect();
+        result.sort_unstable();
+        let expected: Vec<u32> = (0..1 << bits).collect();
+        assert_eq!(expected, result);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_too_many_bits() {
+        bit_reverse(0, 33);
+    }
+}
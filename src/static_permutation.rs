@@ -0,0 +1,115 @@
+//! A hashed permutation whose length is known at compile time.
+
+use crate::kensler::kensler_permute;
+
+/// A hashed permutation over `0..LEN`, where the length is a compile-time constant.
+///
+/// Because the length is known up front, `shuffle` doesn't need to return a `Result`. Inputs are
+/// only range checked with a `debug_assert`. In release builds an input outside of `0..LEN` is
+/// reduced modulo `LEN` first, so it shuffles to the same value as `input % LEN` rather than
+/// returning an error.
+///
+/// A length of zero is rejected at compile time:
+///
+/// ```compile_fail
+/// # use hashed_permutation::StaticPermutation;
+/// let perm = StaticPermutation::<0>::new(1234);
+/// ```
+///
+/// ```
+/// # use hashed_permutation::StaticPermutation;
+/// let perm = StaticPermutation::<10>::new(1234);
+/// let permuted_number = perm.shuffle(0);
+/// assert!(permuted_number < 10);
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct StaticPermutation<const LEN: u32> {
+    /// The random seed that dictates which permutation you want to use
+    seed: u32,
+}
+
+impl<const LEN: u32> StaticPermutation<LEN> {
+    /// The upper bound on the range of numbers to shuffle (from `0..LENGTH`)
+    pub const LENGTH: u32 = LEN;
+
+    /// Evaluating this constant fails to compile if the length is zero.
+    const NONZERO_LENGTH: () = assert!(LEN > 0, "the length of a permutation must be nonzero");
+
+    /// Create a new instance of the permutation with the given seed
+    pub const fn new(seed: u32) -> Self {
+        #[allow(clippy::let_unit_value)]
+        let () = Self::NONZERO_LENGTH;
+        Self { seed }
+    }
+
+    /// The seed that dictates which permutation is used
+    pub const fn seed(&self) -> u32 {
+        self.seed
+    }
+
+    /// Shuffle or permute a particular value.
+    ///
+    /// `input` must be within `0..LEN`. This is checked in debug builds. In release builds an
+    /// input outside of that range is reduced modulo `LEN`, since walking the cycle of an index
+    /// past the end of the range might never land back within it.
+    pub fn shuffle(&self, input: u32) -> u32 {
+        debug_assert!(
+            input < LEN,
+            "Attempted to shuffle index {}, but the length of the array is {}",
+            input,
+            LEN
+        );
+        kensler_permute(input % LEN, LEN, self.seed)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::HashedPermutation;
+    use std::num::NonZeroU32;
+
+    /// Check that a static permutation matches the dynamic permutation with the same parameters
+    fn check_matches_dynamic<const LEN: u32>(seed: u32) {
+        let perm = StaticPermutation::<LEN>::new(seed);
+        let dynamic = HashedPermutation::new_with_seed(NonZeroU32::new(LEN).unwrap(), seed);
+        let mut seen = vec![false; LEN as usize];
+
+        for i in 0..LEN {
+            let res = perm.shuffle(i);
+            assert_eq!(res, dynamic.shuffle(i).unwrap());
+            assert!(!seen[res as usize]);
+            seen[res as usize] = true;
+        }
+        assert_eq!(perm.seed(), seed);
+    }
+
+    #[test]
+    fn test_matches_dynamic() {
+        check_matches_dynamic::<100>(100);
+        check_matches_dynamic::<5>(5);
+        check_matches_dynamic::<13>(13);
+        check_matches_dynamic::<128>(128);
+        check_matches_dynamic::<249>(249);
+        check_matches_dynamic::<1>(u32::MAX);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic]
+    fn test_out_of_range_debug() {
+        StaticPermutation::<10>::new(0).shuffle(10);
+    }
+
+    #[test]
+    #[cfg(not(debug_assertions))]
+    // Without the debug check, an out-of-range input should still terminate, and land on the same
+    // value as the input reduced modulo the length.
+    fn test_out_of_range_release() {
+        let perm = StaticPermutation::<10>::new(0);
+
+        for &input in &[10, 15, 99, u32::MAX] {
+            assert_eq!(perm.shuffle(input), perm.shuffle(input % 10));
+        }
+    }
+}
@@ -0,0 +1,405 @@
+//! Statistical diagnostics for judging how well a permutation mixes its indices.
+
+use crate::{HashedPermutation, Permute};
+
+/// The maximum number of buckets used to bin displacements when estimating seed quality
+const QUALITY_BUCKETS: u32 = 16;
+
+/// A small set of statistics describing how uniformly a permutation displaces indices.
+///
+/// The displacement of an index `i` is `(shuffle(i) - i) mod length`. A well mixed permutation
+/// displaces indices roughly uniformly, while a pathological one tends to favor a few
+/// displacements (a rotation, for example, displaces every index by the same amount).
+#[derive(Clone, Debug, PartialEq)]
+pub struct SeedQuality {
+    /// The number of indices that were sampled
+    pub samples: u32,
+
+    /// The number of sampled indices that map to themselves
+    pub fixed_points: u32,
+
+    /// The mean displacement, normalized by the length of the permutation. This should be close
+    /// to 0.5 for a well mixed permutation.
+    pub mean_displacement: f64,
+
+    /// Pearson's chi-squared statistic of the displacements against a uniform distribution.
+    /// Lower values mean the displacements are spread more evenly.
+    pub chi_squared: f64,
+}
+
+/// The results of exhaustively checking a permutation with [`HashedPermutation::verify`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VerificationReport {
+    /// Whether every input maps to a distinct output within `0..length`
+    pub is_bijection: bool,
+
+    /// The number of inputs whose output is outside of `0..length`
+    pub out_of_range: u32,
+
+    /// The number of inputs whose output was already produced by a smaller input
+    pub duplicates: u32,
+
+    /// The number of inputs that map to themselves
+    pub fixed_points: u32,
+
+    /// The largest output of the permutation, which should be `length - 1`
+    pub max_index: u32,
+}
+
+impl HashedPermutation {
+    /// Exhaustively check the permutation, reporting any anomalies.
+    ///
+    /// This shuffles every input in `0..length` once, checking that each output is within range
+    /// and hasn't been seen before. It takes O(n) time and allocates one flag per element, so it's
+    /// meant for validation tools and tests rather than hot paths.
+    pub fn verify(&self) -> VerificationReport {
+        let n = self.length.get();
+        let mut seen = vec![false; n as usize];
+        let mut report = VerificationReport {
+            is_bijection: false,
+            out_of_range: 0,
+            duplicates: 0,
+            fixed_points: 0,
+            max_index: 0,
+        };
+
+        for i in 0..n {
+            let output = self.permute(i);
+            report.max_index = report.max_index.max(output);
+
+            if output == i {
+                report.fixed_points += 1;
+            }
+            match seen.get_mut(output as usize) {
+                Some(seen) if *seen => report.duplicates += 1,
+                Some(seen) => *seen = true,
+                None => report.out_of_range += 1,
+            }
+        }
+        report.is_bijection = report.out_of_range == 0 && report.duplicates == 0;
+        report
+    }
+
+    /// Estimate how uniformly this permutation displaces indices.
+    ///
+    /// This samples the first `max_samples` indices (or every index, if the permutation is
+    /// shorter than that), so the cost is bounded regardless of the length of the permutation.
+    /// This is meant as a quick sanity check for detecting pathological seeds, not a rigorous
+    /// statistical test.
+    pub fn verify_seed_quality(&self, max_samples: u32) -> SeedQuality {
+        let n = self.length.get();
+        let samples = max_samples.min(n);
+        let buckets = QUALITY_BUCKETS.min(n);
+        let mut counts = vec![0_u32; buckets as usize];
+        let mut fixed_points = 0;
+        let mut total_displacement = 0.0;
+
+        for i in 0..samples {
+            let displacement = displacement(self, i);
+
+            if displacement == 0 {
+                fixed_points += 1;
+            }
+            total_displacement += f64::from(displacement) / f64::from(n);
+            counts[(u64::from(displacement) * u64::from(buckets) / u64::from(n)) as usize] += 1;
+        }
+
+        if samples == 0 {
+            return SeedQuality {
+                samples,
+                fixed_points,
+                mean_displacement: 0.0,
+                chi_squared: 0.0,
+            };
+        }
+        let expected = f64::from(samples) / f64::from(buckets);
+        let chi_squared = counts
+            .iter()
+            .map(|&count| (f64::from(count) - expected).powi(2) / expected)
+            .sum();
+
+        SeedQuality {
+            samples,
+            fixed_points,
+            mean_displacement: total_displacement / f64::from(samples),
+            chi_squared,
+        }
+    }
+
+    /// Count how many indices fall into each range of displacements.
+    ///
+    /// The displacements `0..length` are split into `buckets` equal ranges, and `result[k]` is the
+    /// number of indices whose displacement lands in the `k`-th range. A well mixed permutation
+    /// should fill the buckets roughly evenly. If there are more buckets than displacements, some
+    /// of the buckets will always be empty.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `buckets` is zero.
+    pub fn displacement_histogram(&self, buckets: u32) -> Vec<u32> {
+        assert!(buckets > 0, "the histogram needs at least one bucket");
+        displacement_counts(self, buckets)
+    }
+
+    /// Estimate how uniformly the permutation displaces indices, as a single score in `[0, 1]`.
+    ///
+    /// This bins the displacement of every index like
+    /// [`HashedPermutation::displacement_histogram`] does, and computes the Shannon entropy of the
+    /// bins, normalized by the largest entropy they could have. A score of 1 means the
+    /// displacements are spread evenly across the bins, while a rotation, which displaces every
+    /// index by the same amount, scores 0. This is a rough tool for comparing seeds rather than a
+    /// rigorous statistic, and it takes O(n) time.
+    pub fn entropy_estimate(&self) -> f64 {
+        displacement_entropy(self)
+    }
+
+    /// Count the inversions of the permutation.
+    ///
+    /// An inversion is a pair of indices `i < j` with `shuffle(i) > shuffle(j)`. The identity has
+    /// no inversions, and the reversal has the most, `length * (length - 1) / 2`. This walks the
+    /// permutation once while maintaining a Fenwick tree over the outputs seen so far, so it takes
+    /// O(n log n) time and allocates one counter per element.
+    pub fn inversions(&self) -> u64 {
+        count_inversions(
+            (0..self.length.get()).map(|i| self.permute(i)),
+            self.length.get(),
+        )
+    }
+}
+
+/// The distance an index is moved by a permutation, wrapping around the end of the range
+///
+/// `input` must be within `0..perm.len()`.
+fn displacement<P: Permute>(perm: &P, input: u32) -> u32 {
+    let n = u64::from(perm.len());
+    let output = perm
+        .shuffle(input)
+        .expect("the displacement is only taken for indices within the permutation");
+    ((u64::from(output) + n - u64::from(input)) % n) as u32
+}
+
+/// Bin the displacement of every index of a permutation into `buckets` equal ranges, like
+/// [`HashedPermutation::displacement_histogram`]
+///
+/// `buckets` must be nonzero.
+fn displacement_counts<P: Permute>(perm: &P, buckets: u32) -> Vec<u32> {
+    let n = perm.len();
+    let mut counts = vec![0_u32; buckets as usize];
+
+    for i in 0..n {
+        let displacement = displacement(perm, i);
+        counts[(u64::from(displacement) * u64::from(buckets) / u64::from(n)) as usize] += 1;
+    }
+    counts
+}
+
+/// The normalized entropy of the displacements of a permutation, which is what
+/// [`HashedPermutation::entropy_estimate`] reports
+fn displacement_entropy<P: Permute>(perm: &P) -> f64 {
+    let buckets = QUALITY_BUCKETS.min(perm.len());
+
+    if buckets == 0 {
+        return 0.0;
+    }
+    normalized_entropy(&displacement_counts(perm, buckets))
+}
+
+/// The Shannon entropy of a histogram, divided by the entropy of a uniform histogram with the
+/// same number of bins, so the result is within `[0, 1]`.
+///
+/// A histogram with a single bin, or without any samples, has an entropy of zero.
+fn normalized_entropy(counts: &[u32]) -> f64 {
+    let total: f64 = counts.iter().map(|&count| f64::from(count)).sum();
+
+    if counts.len() < 2 || total == 0.0 {
+        return 0.0;
+    }
+    let entropy: f64 = counts
+        .iter()
+        .filter(|&&count| count > 0)
+        .map(|&count| {
+            let p = f64::from(count) / total;
+            -p * p.log2()
+        })
+        .sum();
+    // Rounding can push the ratio slightly past 1 for a perfectly uniform histogram
+    (entropy / (counts.len() as f64).log2()).min(1.0)
+}
+
+/// Count the pairs `i < j` with `values[i] > values[j]`, where the values are distinct and within
+/// `0..n`.
+///
+/// This walks the values once while maintaining a Fenwick tree over the values seen so far, so it
+/// takes O(n log n) time and allocates one counter per possible value.
+pub(crate) fn count_inversions(values: impl Iterator<Item = u32>, n: u32) -> u64 {
+    // `tree[k]` holds the number of values seen so far within a range of values ending at
+    // `k - 1`, where the size of the range is the lowest set bit of `k`.
+    let mut tree = vec![0_u32; n as usize + 1];
+    let mut inversions = 0;
+
+    for (i, value) in values.enumerate() {
+        // The number of values seen so far that are at most `value`
+        let mut not_inverted = 0;
+        let mut k = value as usize + 1;
+
+        while k > 0 {
+            not_inverted += tree[k];
+            k &= k - 1;
+        }
+        inversions += u64::from(i as u32 - not_inverted);
+        let mut k = value as usize + 1;
+
+        while k < tree.len() {
+            tree[k] += 1;
+            k += k & k.wrapping_neg();
+        }
+    }
+    inversions
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::error::PermutationResult;
+    use crate::test_utils::edge_lengths_and_seeds;
+    use std::num::NonZeroU32;
+
+    /// The rotation `i -> (i + offset) % length`, which is about as poorly mixed as a permutation
+    /// can be
+    struct Rotation {
+        length: u32,
+        offset: u32,
+    }
+
+    impl Permute for Rotation {
+        fn shuffle(&self, input: u32) -> PermutationResult<u32> {
+            Ok((input + self.offset) % self.length)
+        }
+
+        fn len(&self) -> u32 {
+            self.length
+        }
+    }
+
+    #[test]
+    fn test_seed_quality() {
+        let (lengths, seeds) = edge_lengths_and_seeds();
+
+        for (&length, seed) in lengths.iter().zip(seeds) {
+            let perm = HashedPermutation::new_with_seed(length, seed);
+            let length = length.get();
+
+            for &max_samples in &[0, 1, 64, 1000] {
+                let quality = perm.verify_seed_quality(max_samples);
+                assert_eq!(quality.samples, max_samples.min(length));
+                assert!(quality.fixed_points <= quality.samples);
+                assert!((0.0..1.0).contains(&quality.mean_displacement));
+                assert!(quality.chi_squared >= 0.0);
+                assert!(quality.chi_squared.is_finite());
+            }
+        }
+    }
+
+    #[test]
+    fn test_verify() {
+        let (lengths, seeds) = edge_lengths_and_seeds();
+
+        for (&length, seed) in lengths.iter().zip(seeds) {
+            let perm = HashedPermutation::new_with_seed(length, seed);
+            let length = length.get();
+            let report = perm.verify();
+            assert!(report.is_bijection);
+            assert_eq!(report.out_of_range, 0);
+            assert_eq!(report.duplicates, 0);
+            assert_eq!(report.max_index, length - 1);
+            assert_eq!(
+                report.fixed_points as usize,
+                (0..length)
+                    .filter(|&i| perm.shuffle(i).unwrap() == i)
+                    .count()
+            );
+        }
+    }
+
+    #[test]
+    fn test_displacement_histogram() {
+        let (lengths, seeds) = edge_lengths_and_seeds();
+
+        for (&length, seed) in lengths.iter().zip(seeds) {
+            let perm = HashedPermutation::new_with_seed(length, seed);
+            let length = length.get();
+
+            for &buckets in &[1, 4, 16, length, length + 3] {
+                let histogram = perm.displacement_histogram(buckets);
+                assert_eq!(histogram.len(), buckets as usize);
+                assert_eq!(histogram.iter().sum::<u32>(), length);
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_displacement_histogram_no_buckets() {
+        let perm = HashedPermutation::new_with_seed(NonZeroU32::new(10).unwrap(), 0);
+        perm.displacement_histogram(0);
+    }
+
+    #[test]
+    fn test_entropy_estimate() {
+        let (lengths, seeds) = edge_lengths_and_seeds();
+
+        for (&length, seed) in lengths.iter().zip(seeds) {
+            let perm = HashedPermutation::new_with_seed(length, seed);
+            let entropy = perm.entropy_estimate();
+            assert!((0.0..=1.0).contains(&entropy));
+        }
+        // A permutation of one element is the identity, which has no spread at all
+        let identity = HashedPermutation::new_with_seed(NonZeroU32::new(1).unwrap(), 0);
+        assert_eq!(identity.entropy_estimate(), 0.0);
+
+        // A rotation displaces every index by the same amount, so it has the lowest possible score
+        for &(length, offset) in &[(100, 0), (100, 37), (249, 248), (10_000, 1234)] {
+            let rotation = Rotation { length, offset };
+            assert_eq!(displacement_entropy(&rotation), 0.0);
+        }
+
+        // A hashed permutation over a real length should spread its displacements almost evenly
+        for &seed in &[0, 100, 0x5eed, u32::MAX] {
+            let perm = HashedPermutation::new_with_seed(NonZeroU32::new(10_000).unwrap(), seed);
+            assert!(perm.entropy_estimate() > 0.95);
+        }
+    }
+
+    #[test]
+    fn test_normalized_entropy() {
+        // Every index displaced by the same amount, like the identity or a rotation
+        assert_eq!(normalized_entropy(&[100, 0, 0, 0]), 0.0);
+        assert_eq!(normalized_entropy(&[0, 0, 0, 0]), 0.0);
+        assert_eq!(normalized_entropy(&[7]), 0.0);
+        assert!((normalized_entropy(&[25, 25, 25, 25]) - 1.0).abs() < 1e-12);
+
+        let skewed = normalized_entropy(&[70, 10, 10, 10]);
+        assert!(0.0 < skewed && skewed < 1.0);
+    }
+
+    #[test]
+    // The Fenwick tree count should match a brute force count over every pair.
+    fn test_inversions() {
+        for length in 1..=40 {
+            for seed in 0..8 {
+                let perm = HashedPermutation::new_with_seed(NonZeroU32::new(length).unwrap(), seed);
+                let values: Vec<u32> = perm.iter().collect();
+                let mut expected = 0;
+
+                for i in 0..values.len() {
+                    for j in i + 1..values.len() {
+                        if values[i] > values[j] {
+                            expected += 1;
+                        }
+                    }
+                }
+                assert_eq!(perm.inversions(), expected);
+            }
+        }
+    }
+}
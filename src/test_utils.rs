@@ -0,0 +1,27 @@
+//! Fixtures that are shared by the tests of every module.
+
+use std::num::NonZeroU32;
+
+/// A convenient helper method that returns a pair of lengths and seeds (in that order).
+///
+/// This method defines the lengths and the seeds for the test cases, since these are reused
+/// in the tests, and it's best practice to consolidate them in one place so code is not
+/// repeated.
+pub(crate) fn lengths_and_seeds() -> (Vec<NonZeroU32>, Vec<u32>) {
+    let lengths: Vec<NonZeroU32> = vec![100, 5, 13, 128, 249]
+        .iter()
+        .map(|&x| NonZeroU32::new(x).unwrap())
+        .collect();
+    let seeds = vec![100, 5, 13, 128, 249];
+    assert_eq!(lengths.len(), seeds.len());
+    (lengths, seeds)
+}
+
+/// The same lengths and seeds as [`lengths_and_seeds`], plus a few edge cases: a permutation of a
+/// single element, a longer permutation, and the largest seed.
+pub(crate) fn edge_lengths_and_seeds() -> (Vec<NonZeroU32>, Vec<u32>) {
+    let (mut lengths, mut seeds) = lengths_and_seeds();
+    lengths.extend([1, 1000].iter().map(|&x| NonZeroU32::new(x).unwrap()));
+    seeds.extend([0, u32::MAX].iter());
+    (lengths, seeds)
+}
@@ -0,0 +1,103 @@
+//! A stateful sampler that draws distinct indices without replacement.
+
+use crate::HashedPermutation;
+
+/// Draws every index of `0..length` exactly once, in the order given by a permutation.
+///
+/// This is a reproducible replacement for calling `rng.gen_range(0..length)` when every draw has
+/// to be distinct. Unlike `PermutationSampler`, it draws in the order of the permutation it's
+/// given instead of picking each draw with an RNG, so it doesn't depend on `rand` and doesn't
+/// allocate.
+///
+/// ```
+/// # use hashed_permutation::{HashedPermutation, UniqueSampler};
+/// use std::num::NonZeroU32;
+///
+/// let perm = HashedPermutation::new_with_seed(NonZeroU32::new(10).unwrap(), 1234);
+/// let mut sampler = UniqueSampler::new(perm);
+///
+/// let first = sampler.next().unwrap();
+/// assert!(first < 10);
+/// assert_eq!(sampler.drawn(), 1);
+/// ```
+#[derive(Clone, Debug)]
+pub struct UniqueSampler {
+    /// The permutation that values are drawn from
+    permutation: HashedPermutation,
+
+    /// The number of values that have been drawn so far
+    drawn: u32,
+}
+
+impl UniqueSampler {
+    /// Create a new sampler that draws values from a permutation
+    pub fn new(permutation: HashedPermutation) -> Self {
+        Self {
+            permutation,
+            drawn: 0,
+        }
+    }
+
+    /// The number of values that have been drawn from the sampler so far
+    pub fn drawn(&self) -> u32 {
+        self.drawn
+    }
+}
+
+impl From<HashedPermutation> for UniqueSampler {
+    fn from(permutation: HashedPermutation) -> Self {
+        Self::new(permutation)
+    }
+}
+
+impl Iterator for UniqueSampler {
+    type Item = u32;
+
+    /// Draw the next unused index, or `None` once every index has been drawn
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.drawn == self.permutation.length.get() {
+            return None;
+        }
+        let elem = self.permutation.permute(self.drawn);
+        self.drawn += 1;
+        Some(elem)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = (self.permutation.length.get() - self.drawn) as usize;
+        (remaining, Some(remaining))
+    }
+}
+
+impl ExactSizeIterator for UniqueSampler {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::edge_lengths_and_seeds;
+    use std::collections::HashSet;
+
+    #[test]
+    // The sampler should draw every index exactly once, and then keep returning `None`.
+    fn test_exhaustion() {
+        let (lengths, seeds) = edge_lengths_and_seeds();
+
+        for (&length, seed) in lengths.iter().zip(seeds) {
+            let perm = HashedPermutation::new_with_seed(length, seed);
+            let length = length.get();
+            let mut sampler = UniqueSampler::new(perm);
+            let mut set = HashSet::with_capacity(length as usize);
+
+            for i in 0..length {
+                assert_eq!(sampler.len(), (length - i) as usize);
+                let elem = sampler.next().unwrap();
+                assert!(elem < length);
+                assert!(set.insert(elem));
+            }
+            assert_eq!(sampler.drawn(), length);
+            assert_eq!(sampler.next(), None);
+            assert_eq!(sampler.next(), None);
+            assert_eq!(sampler.drawn(), length);
+        }
+    }
+}